@@ -0,0 +1,159 @@
+use crate::server;
+use serde::Deserialize;
+use std::fs;
+
+/// A group of ports sharing a risk profile, e.g. remote administration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Category {
+    pub name: String,
+    /// Drawio style appended to the port style, usually fill and stroke colour.
+    pub style: String,
+    /// Service names as reported by nmap.
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// Port numbers, used if the service name does not match any category.
+    #[serde(default)]
+    pub ports: Vec<u16>,
+}
+
+/// Mapping of ports to categories, loaded from TOML files like:
+///
+/// ```toml
+/// [[category]]
+/// name = "Remote administration"
+/// style = "fillColor=#f8cecc;strokeColor=#b85450;"
+/// services = ["ssh", "ms-wbt-server", "vnc"]
+/// ports = [22, 3389, 5900]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Categories {
+    #[serde(rename = "category")]
+    pub categories: Vec<Category>,
+}
+
+impl Default for Categories {
+    fn default() -> Categories {
+        let category = |name: &str, style: &str, services: &[&str], ports: &[u16]| Category {
+            name: name.to_string(),
+            style: style.to_string(),
+            services: services.iter().map(|s| s.to_string()).collect(),
+            ports: ports.to_vec(),
+        };
+
+        Categories {
+            categories: vec![
+                category(
+                    "Legacy cleartext",
+                    "fillColor=#e51400;strokeColor=#b20000;fontColor=#ffffff;",
+                    &[
+                        "telnet", "ftp", "tftp", "exec", "login", "shell", "pop3", "imap",
+                    ],
+                    &[21, 23, 69, 110, 143, 512, 513, 514],
+                ),
+                category(
+                    "Remote administration",
+                    "fillColor=#f8cecc;strokeColor=#b85450;",
+                    &["ssh", "ms-wbt-server", "vnc", "wsman", "wsmans", "x11"],
+                    &[22, 3389, 5900, 5901, 5985, 5986, 6000],
+                ),
+                category(
+                    "Databases",
+                    "fillColor=#e1d5e7;strokeColor=#9673a6;",
+                    &[
+                        "mysql",
+                        "ms-sql-s",
+                        "postgresql",
+                        "oracle-tns",
+                        "mongodb",
+                        "redis",
+                        "cassandra",
+                        "elasticsearch",
+                    ],
+                    &[1433, 1521, 3306, 5432, 6379, 9042, 9200, 27017],
+                ),
+                category(
+                    "File sharing",
+                    "fillColor=#ffe6cc;strokeColor=#d79b00;",
+                    &["microsoft-ds", "netbios-ssn", "nfs", "afp", "rsync"],
+                    &[139, 445, 548, 873, 2049],
+                ),
+                category(
+                    "Web",
+                    "fillColor=#dae8fc;strokeColor=#6c8ebf;",
+                    &["http", "https", "http-proxy", "http-alt", "ssl/http"],
+                    &[80, 443, 8000, 8080, 8443],
+                ),
+                category(
+                    "Mail",
+                    "fillColor=#d5e8d4;strokeColor=#82b366;",
+                    &["smtp", "submission", "smtps", "imaps", "pop3s"],
+                    &[25, 465, 587, 993, 995],
+                ),
+                category(
+                    "Directory",
+                    "fillColor=#fff2cc;strokeColor=#d6b656;",
+                    &[
+                        "ldap",
+                        "ldapssl",
+                        "kerberos-sec",
+                        "kpasswd5",
+                        "globalcatLDAP",
+                    ],
+                    &[88, 389, 464, 636, 3268, 3269],
+                ),
+            ],
+        }
+    }
+}
+
+impl Categories {
+    /// Loads the built-in mapping for `default`, or a category file otherwise.
+    pub fn load(name: &str) -> Result<Categories, String> {
+        match name {
+            "default" => Ok(Categories::default()),
+            filename => {
+                let content =
+                    fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?;
+                toml::from_str(&content).map_err(|e| format!("{}: {}", filename, e))
+            }
+        }
+    }
+
+    /// Finds the category of a port, matching the service name before the
+    /// port number.
+    pub fn classify(&self, port: u16, service: &str) -> Option<&Category> {
+        self.categories
+            .iter()
+            .find(|c| c.services.iter().any(|s| s == service))
+            .or_else(|| self.categories.iter().find(|c| c.ports.contains(&port)))
+    }
+
+    pub fn classify_item(&self, item: &server::Item) -> Option<&Category> {
+        match item {
+            server::Item::Port(port, _, service, _) => self.classify(*port, service),
+            _ => None,
+        }
+    }
+
+    /// Counts the ports of every category on the given servers, in the order
+    /// of the categories. Categories without ports are left out.
+    pub fn count(&self, servers: &[&server::Server]) -> Vec<(&Category, usize)> {
+        self.categories
+            .iter()
+            .map(|category| {
+                let ports = servers
+                    .iter()
+                    .flat_map(|s| s.items.iter())
+                    .filter(|item| {
+                        self.classify_item(item)
+                            .is_some_and(|c| std::ptr::eq(c, category))
+                    })
+                    .count();
+                (category, ports)
+            })
+            .filter(|(_, ports)| *ports > 0)
+            .collect()
+    }
+}
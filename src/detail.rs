@@ -1,93 +1,293 @@
 use crate::parser;
 use crate::parser::Host;
 use crate::parser::Metadata;
+use crate::selection::PortSelection;
 
 #[derive(Debug, Clone)]
 pub struct Hostservice {
     pub ip: String,
+    pub protocol: String,
     pub port: u16,
     pub metadata: Metadata,
+    /// Shown in an extra column if the table groups different services.
+    pub detail: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-pub struct Service {
-    pub service: String,
-    pub product: String,
+/// What the service tables are grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Grouping {
+    /// One table per service identity, see `Granularity`.
+    #[default]
+    Service,
+    /// One table per service name, the versions in a column.
+    Name,
+    /// One table per port number.
+    Port,
+    /// One table per product regardless of its version and service name.
+    Product,
+    /// One table per host, listing its services.
+    Host,
+}
+
+impl Grouping {
+    pub fn parse(name: &str) -> Option<Grouping> {
+        match name {
+            "service" => Some(Grouping::Service),
+            "name" => Some(Grouping::Name),
+            "port" => Some(Grouping::Port),
+            "product" => Some(Grouping::Product),
+            "host" => Some(Grouping::Host),
+            _ => None,
+        }
+    }
+}
+
+/// How finely ports are grouped into service tables.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Granularity {
+    /// One table per service name, e.g. all "ssh" ports.
+    Name,
+    /// One table per service name and product.
+    Product,
+    /// One table per service name, product, version and extra info.
+    #[default]
+    Version,
+}
+
+impl Granularity {
+    pub fn parse(name: &str) -> Option<Granularity> {
+        match name {
+            "name" => Some(Granularity::Name),
+            "product" => Some(Granularity::Product),
+            "version" => Some(Granularity::Version),
+            _ => None,
+        }
+    }
+}
+
+/// Identifies a service table. Fields finer than the granularity are left
+/// out, ports without a service name share the unidentified table.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Identity {
+    pub name: Option<String>,
+    pub product: Option<String>,
     pub version: Option<String>,
     pub extrainfo: Option<String>,
-    pub hosts: Vec<Hostservice>,
+}
+
+impl Identity {
+    pub fn new(service: Option<&parser::Service>, granularity: Granularity) -> Identity {
+        // nmap names services it could not identify "unknown"
+        let service = match service {
+            Some(service) if service.name.as_ref().is_some_and(|n| n != "unknown") => service,
+            _ => return Identity::default(),
+        };
+
+        let mut identity = Identity {
+            name: service.name.clone(),
+            ..Identity::default()
+        };
+        if granularity != Granularity::Name {
+            identity.product = service.product.clone();
+        }
+        if granularity == Granularity::Version {
+            identity.version = service.version.clone();
+            identity.extrainfo = service.extrainfo.clone();
+        }
+        identity
+    }
+
+    pub fn is_identified(&self) -> bool {
+        self.name.is_some()
+    }
+
+    /// Identifies the table across scans.
+    pub fn key(&self) -> String {
+        if !self.is_identified() {
+            return "unidentified".to_string();
+        }
+
+        [&self.name, &self.product, &self.version, &self.extrainfo]
+            .iter()
+            .filter_map(|s| s.as_deref())
+            .collect::<Vec<&str>>()
+            .join("-")
+    }
+}
+
+/// What the rows of a table have in common.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Group {
+    Service(Identity),
+    Port {
+        protocol: String,
+        port: u16,
+    },
+    /// The product, `None` for ports without one.
+    Product(Option<String>),
+    /// The first address of the host and its name.
+    Host {
+        address: String,
+        name: String,
+    },
+}
+
+impl Group {
+    /// Identifies the table across scans.
+    pub fn key(&self) -> String {
+        match self {
+            Group::Service(identity) => identity.key(),
+            Group::Port { protocol, port } => format!("port-{}-{}", protocol, port),
+            Group::Product(Some(product)) => format!("product-{}", product),
+            Group::Product(None) => "unidentified".to_string(),
+            Group::Host { address, .. } => format!("host-{}", address),
+        }
+    }
+
+    fn is_identified(&self) -> bool {
+        match self {
+            Group::Service(identity) => identity.is_identified(),
+            Group::Product(product) => product.is_some(),
+            _ => true,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
+pub struct Service {
+    pub group: Group,
+    pub hosts: Vec<Hostservice>,
+}
+
+impl Hostservice {
+    /// Identifies the row within its table across scans.
+    pub fn key(&self) -> String {
+        format!("{}-{}-{}", self.ip, self.protocol, self.port)
+    }
+}
+
+impl Service {
+    /// Identifies the table across scans.
+    pub fn key(&self) -> String {
+        self.group.key()
+    }
+}
+
+/// Joins the given fields of a service, e.g. "OpenSSH 7.4".
+fn describe(fields: &[&Option<String>]) -> Option<String> {
+    let fields: Vec<&str> = fields.iter().filter_map(|f| f.as_deref()).collect();
+    match fields.is_empty() {
+        true => None,
+        false => Some(fields.join(" ")),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Tables {
+    pub grouping: Grouping,
+    pub granularity: Granularity,
+    /// Ports left out are not listed in any table.
+    pub ports: PortSelection,
     pub services: Vec<Service>,
 }
 
 impl Tables {
-    pub fn new() -> Tables {
+    pub fn new(grouping: Grouping, granularity: Granularity, ports: PortSelection) -> Tables {
         Tables {
+            grouping,
+            granularity,
+            ports,
             services: Vec::new(),
         }
     }
 
-    fn correct_table(service: &Service, portservice: &parser::Service) -> bool {
-        service.service == portservice.name.clone().unwrap()
-            && service.product == portservice.product.clone().unwrap()
-            && service.version == portservice.version
-            && service.extrainfo == portservice.extrainfo
+    /// The table a port belongs to and the detail shown in its row.
+    fn group(&self, host: &Host, port: &parser::Port) -> (Group, Option<String>) {
+        let unknown = parser::Service {
+            name: None,
+            product: None,
+            version: None,
+            extrainfo: None,
+        };
+        let service = port.service.as_ref().unwrap_or(&unknown);
+
+        match self.grouping {
+            Grouping::Service => (
+                Group::Service(Identity::new(port.service.as_ref(), self.granularity)),
+                None,
+            ),
+            Grouping::Name => (
+                Group::Service(Identity::new(port.service.as_ref(), Granularity::Name)),
+                describe(&[&service.product, &service.version]),
+            ),
+            Grouping::Port => (
+                Group::Port {
+                    protocol: port.protocol.clone(),
+                    port: port.port,
+                },
+                describe(&[&service.name, &service.product, &service.version]),
+            ),
+            Grouping::Product => (
+                Group::Product(service.product.clone()),
+                describe(&[&service.name, &service.version]),
+            ),
+            Grouping::Host => {
+                let address = host.key().unwrap_or_default();
+                let name = host.hostnames.first().cloned().unwrap_or(address.clone());
+                (
+                    Group::Host { address, name },
+                    describe(&[&service.name, &service.product, &service.version]),
+                )
+            }
+        }
     }
 
-    fn add_hostservice(service: &mut Service, addresses: &Vec<parser::IpAddr>, port: u16) {
+    fn add_hostservice(
+        service: &mut Service,
+        addresses: &[parser::IpAddr],
+        port: &parser::Port,
+        detail: Option<String>,
+    ) {
         for address in addresses {
             match address.addr_type {
                 parser::AddrType::MAC => {}
                 _ => service.hosts.push(Hostservice {
                     ip: address.address.clone(),
-                    port,
-                    metadata: Metadata::None,
+                    protocol: port.protocol.clone(),
+                    port: port.port,
+                    metadata: port.metadata.clone(),
+                    detail: detail.clone(),
                 }),
             }
         }
     }
 
     pub fn add_host(&mut self, host: &Host) {
-        println!(
-            "ports: {:?}",
-            host.ports
-                .clone()
-                .iter()
-                .map(|p| p.port)
-                .collect::<Vec<u16>>()
-        );
-        for port in host.ports.clone() {
-            println!(
-                "{}:{} ==> {:?}",
-                host.addresses.first().unwrap().address,
-                port.port,
-                &port.service
-            );
-            if port.service.is_some() {
-                let mut add_service = true;
-                for service in self.services.iter_mut() {
-                    if Tables::correct_table(&service, &port.service.clone().unwrap()) {
-                        Tables::add_hostservice(service, &host.addresses, port.port);
-                        add_service = false;
-                        break;
-                    }
-                }
-                if add_service {
-                    let portservice = port.service.clone().unwrap();
+        for port in host.ports.iter().filter(|p| self.ports.shows(p.port)) {
+            let (group, detail) = self.group(host, port);
+            match self.services.iter_mut().find(|s| s.group == group) {
+                Some(service) => Tables::add_hostservice(service, &host.addresses, port, detail),
+                None => {
                     let mut service = Service {
-                        service: portservice.name.unwrap(),
-                        product: portservice.product.unwrap(),
-                        version: portservice.version,
-                        extrainfo: portservice.extrainfo,
+                        group,
                         hosts: Vec::new(),
                     };
-                    Tables::add_hostservice(&mut service, &host.addresses, port.port);
+                    Tables::add_hostservice(&mut service, &host.addresses, port, detail);
                     self.services.push(service);
                 }
             }
         }
     }
+
+    /// Sorts port tables by their number and moves unidentified tables behind
+    /// the others.
+    pub fn sort(&mut self) {
+        if self.grouping == Grouping::Port {
+            self.services.sort_by_key(|s| match s.group {
+                Group::Port { port, .. } => port,
+                _ => 0,
+            });
+        }
+        self.services.sort_by_key(|s| !s.group.is_identified());
+    }
 }
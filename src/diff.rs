@@ -0,0 +1,475 @@
+use crate::parser;
+use crate::parser::Host;
+use crate::parser::Metadata;
+use crate::scope;
+use crate::selection::PortSelection;
+use crate::server;
+use serde::Serialize;
+use serde::Serializer;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+    Unchanged,
+}
+
+impl Change {
+    pub fn symbol(&self) -> &str {
+        match self {
+            Change::Added => "+",
+            Change::Removed => "-",
+            Change::Changed => "~",
+            Change::Unchanged => " ",
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Changed => "changed",
+            Change::Unchanged => "unchanged",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortChange {
+    pub port: u16,
+    pub protocol: String,
+    pub change: Change,
+    #[serde(serialize_with = "port_state")]
+    pub old: Option<parser::Port>,
+    #[serde(serialize_with = "port_state")]
+    pub new: Option<parser::Port>,
+}
+
+impl PortChange {
+    fn compare(old: Option<&parser::Port>, new: Option<&parser::Port>) -> PortChange {
+        let port = new
+            .or(old)
+            .expect("A port has to exist in at least one scan!");
+        let change = match (old, new) {
+            (Some(_), None) => Change::Removed,
+            (None, Some(_)) => Change::Added,
+            (Some(old), Some(new)) => {
+                if old.service != new.service || fingerprints(old) != fingerprints(new) {
+                    Change::Changed
+                } else {
+                    Change::Unchanged
+                }
+            }
+            (None, None) => unreachable!("A port has to exist in at least one scan!"),
+        };
+
+        PortChange {
+            port: port.port,
+            protocol: port.protocol.clone(),
+            change,
+            old: old.cloned(),
+            new: new.cloned(),
+        }
+    }
+
+    pub fn service_changed(&self) -> bool {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => old.service != new.service,
+            _ => false,
+        }
+    }
+
+    pub fn keys_changed(&self) -> bool {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => fingerprints(old) != fingerprints(new),
+            _ => false,
+        }
+    }
+
+    /// Describes the change as a single changelog line, e.g. `+ 443/tcp https`
+    /// or `~ 80/tcp: http nginx 1.18 -> http nginx 1.20`.
+    pub fn describe(&self) -> String {
+        let mut line = format!("{} {}/{}", self.change.symbol(), self.port, self.protocol);
+        if self.service_changed() {
+            line.push_str(&format!(
+                ": {} -> {}",
+                service_description(self.old.as_ref().unwrap()),
+                service_description(self.new.as_ref().unwrap())
+            ));
+        } else {
+            line.push_str(&format!(" {}", service_name(self.current())));
+        }
+        if self.keys_changed() {
+            line.push_str(": host keys changed");
+        }
+        line
+    }
+
+    fn current(&self) -> &parser::Port {
+        self.new.as_ref().or(self.old.as_ref()).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostChange {
+    pub address: String,
+    pub change: Change,
+    #[serde(rename = "hostnames", serialize_with = "hostnames")]
+    pub host: Host,
+    #[serde(serialize_with = "changed_ports")]
+    pub ports: Vec<PortChange>,
+    /// Set by `ScanDiff::mark_scope`.
+    pub out_of_scope: bool,
+}
+
+impl HostChange {
+    fn compare(address: String, old: Option<&Host>, new: Option<&Host>) -> HostChange {
+        let host = new
+            .or(old)
+            .expect("A host has to exist in at least one scan!");
+
+        let mut ports: Vec<PortChange> = Vec::new();
+        let empty = Vec::new();
+        let old_ports = old.map(|h| &h.ports).unwrap_or(&empty);
+        let new_ports = new.map(|h| &h.ports).unwrap_or(&empty);
+
+        for port in old_ports {
+            ports.push(PortChange::compare(
+                Some(port),
+                find_port(new_ports, port.port, &port.protocol),
+            ));
+        }
+        for port in new_ports {
+            if find_port(old_ports, port.port, &port.protocol).is_none() {
+                ports.push(PortChange::compare(None, Some(port)));
+            }
+        }
+        ports.sort_by(|a, b| (a.port, &a.protocol).cmp(&(b.port, &b.protocol)));
+
+        let change = match (old, new) {
+            (Some(_), None) => Change::Removed,
+            (None, Some(_)) => Change::Added,
+            _ if ports.iter().any(|p| p.change != Change::Unchanged) => Change::Changed,
+            _ => Change::Unchanged,
+        };
+
+        HostChange {
+            address,
+            change,
+            host: host.clone(),
+            ports,
+            out_of_scope: false,
+        }
+    }
+
+    /// Address of the host, followed by its first hostname if there is one.
+    pub fn label(&self) -> String {
+        match self.host.hostnames.first() {
+            Some(name) => format!("{} ({})", self.address, name),
+            None => self.address.clone(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.out_of_scope {
+            true => format!("{} {} (out of scope)", self.change.symbol(), self.label()),
+            false => format!("{} {}", self.change.symbol(), self.label()),
+        }
+    }
+
+    /// Itemizes the host like `server::Server::into_items`, but keeps removed
+    /// ports and tags every item with the change it belongs to.
+    pub fn items(&self) -> Vec<(server::Item, Change)> {
+        let mut items = Vec::new();
+        let header = match self.change {
+            Change::Changed => Change::Unchanged,
+            change => change,
+        };
+
+        let mut host = self.host.clone();
+        host.ports = Vec::new();
+        for item in server::Server::into_items(host, &PortSelection::default()).items {
+            items.push((item, header));
+        }
+
+        for port in &self.ports {
+            let current = port.current();
+            items.push((
+                server::Item::Port(
+                    current.port,
+                    current.protocol.clone(),
+                    service_name(current),
+                    server::findings(current),
+                ),
+                port.change,
+            ));
+        }
+
+        items
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanDiff {
+    pub hosts: Vec<HostChange>,
+}
+
+impl ScanDiff {
+    /// Matches the hosts of both scans by `Host::key`. Hosts without a key
+    /// cannot be told apart, they are reported as removed and added.
+    pub fn compare(old: &[Host], new: &[Host]) -> ScanDiff {
+        let mut hosts = Vec::new();
+        let index = |hosts: &[Host]| {
+            let mut index: HashMap<String, usize> = HashMap::new();
+            for (i, host) in hosts.iter().enumerate() {
+                if let Some(key) = host.key() {
+                    index.entry(key).or_insert(i);
+                }
+            }
+            index
+        };
+        let (old_index, new_index) = (index(old), index(new));
+        let address = |key: Option<String>| key.unwrap_or_else(|| "unknown".to_string());
+
+        for host in old {
+            let key = host.key();
+            let other = key
+                .as_ref()
+                .and_then(|k| new_index.get(k))
+                .map(|i| &new[*i]);
+            hosts.push(HostChange::compare(address(key), Some(host), other));
+        }
+        for host in new {
+            let key = host.key();
+            if !key.as_ref().is_some_and(|k| old_index.contains_key(k)) {
+                hosts.push(HostChange::compare(address(key), None, Some(host)));
+            }
+        }
+
+        ScanDiff { hosts }
+    }
+
+    /// Marks the hosts outside the scope.
+    pub fn mark_scope(&mut self, scope: &scope::Scope) {
+        for host in self.hosts.iter_mut() {
+            host.out_of_scope = !scope.contains(&host.host);
+        }
+    }
+
+    /// Returns all hosts that differ between both scans.
+    pub fn changed_hosts(&self) -> Vec<&HostChange> {
+        self.hosts
+            .iter()
+            .filter(|h| h.change != Change::Unchanged)
+            .collect()
+    }
+
+    pub fn changelog(&self) -> String {
+        let mut lines = Vec::new();
+
+        for host in self.changed_hosts() {
+            lines.push(host.describe());
+            for port in host.ports.iter().filter(|p| p.change != Change::Unchanged) {
+                lines.push(format!("  {}", port.describe()));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    /// The changed hosts and their changed ports as JSON. A port of either
+    /// scan is described by its service name, the full service description
+    /// and its SSH host keys, `null` if it is missing from the scan.
+    pub fn json(&self) -> String {
+        #[derive(Serialize)]
+        struct Changes<'a> {
+            hosts: Vec<&'a HostChange>,
+        }
+
+        serde_json::to_string(&Changes {
+            hosts: self.changed_hosts(),
+        })
+        .expect("Changes can always be serialized")
+    }
+}
+
+fn find_port<'a>(ports: &'a [parser::Port], port: u16, protocol: &str) -> Option<&'a parser::Port> {
+    ports
+        .iter()
+        .find(|p| p.port == port && p.protocol == protocol)
+}
+
+fn fingerprints(port: &parser::Port) -> Vec<String> {
+    match &port.metadata {
+        Metadata::SSH(fingerprints) => {
+            let mut fingerprints = fingerprints.clone();
+            fingerprints.sort();
+            fingerprints
+        }
+        Metadata::None => Vec::new(),
+    }
+}
+
+fn service_name(port: &parser::Port) -> String {
+    port.service
+        .as_ref()
+        .and_then(|s| s.name.clone())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Name, product, version and extra info of the service, e.g. `http nginx 1.18`.
+fn service_description(port: &parser::Port) -> String {
+    let fields: Vec<&str> = match &port.service {
+        Some(service) => [
+            &service.name,
+            &service.product,
+            &service.version,
+            &service.extrainfo,
+        ]
+        .iter()
+        .filter_map(|f| f.as_deref())
+        .collect(),
+        None => Vec::new(),
+    };
+
+    match fields.is_empty() {
+        true => "unknown".to_string(),
+        false => fields.join(" "),
+    }
+}
+
+fn hostnames<S: Serializer>(host: &Host, serializer: S) -> Result<S::Ok, S::Error> {
+    host.hostnames.serialize(serializer)
+}
+
+fn changed_ports<S: Serializer>(ports: &[PortChange], serializer: S) -> Result<S::Ok, S::Error> {
+    let changed: Vec<&PortChange> = ports
+        .iter()
+        .filter(|p| p.change != Change::Unchanged)
+        .collect();
+    changed.serialize(serializer)
+}
+
+fn port_state<S: Serializer>(
+    port: &Option<parser::Port>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct State<'a> {
+        name: Option<&'a str>,
+        service: String,
+        keys: Vec<String>,
+    }
+
+    port.as_ref()
+        .map(|p| State {
+            name: p.service.as_ref().and_then(|s| s.name.as_deref()),
+            service: service_description(p),
+            keys: fingerprints(p),
+        })
+        .serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    /// An nmap scan of 10.0.0.1 with the given `<port>` elements.
+    fn scan(ports: &str) -> Vec<Host> {
+        let xml = format!(
+            r#"<?xml version="1.0"?>
+<nmaprun scanner="nmap" args="nmap 10.0.0.1" start="1700000000" version="7.94">
+<host><status state="up"/>
+<address addr="10.0.0.1" addrtype="ipv4"/>
+<ports>{}</ports>
+</host>
+</nmaprun>"#,
+            ports
+        );
+        Parser::parse_reader(xml.as_bytes()).unwrap().1
+    }
+
+    fn port(port: u16, state: &str, service: &str) -> String {
+        format!(
+            r#"<port protocol="tcp" portid="{}"><state state="{}"/>{}</port>"#,
+            port, state, service
+        )
+    }
+
+    #[test]
+    fn closed_ports_are_removed() {
+        let ssh = r#"<service name="ssh"/>"#;
+        let old = scan(&port(22, "open", ssh));
+        let new = scan(&(port(22, "closed", ssh) + &port(443, "filtered", "")));
+
+        let changes = ScanDiff::compare(&old, &new);
+        assert_eq!(changes.changelog(), "~ 10.0.0.1\n  - 22/tcp ssh");
+
+        let changes = ScanDiff::compare(&new, &old);
+        assert_eq!(changes.changelog(), "~ 10.0.0.1\n  + 22/tcp ssh");
+    }
+
+    #[test]
+    fn renamed_services_are_described() {
+        let old = scan(&port(80, "open", r#"<service name="http"/>"#));
+        let new = scan(&port(80, "open", r#"<service name="https"/>"#));
+
+        let changes = ScanDiff::compare(&old, &new);
+        assert_eq!(changes.hosts.len(), 1);
+        assert_eq!(changes.hosts[0].change, Change::Changed);
+        assert_eq!(changes.hosts[0].ports[0].change, Change::Changed);
+        assert_eq!(changes.changelog(), "~ 10.0.0.1\n  ~ 80/tcp: http -> https");
+        assert_eq!(
+            changes.json(),
+            concat!(
+                r#"{"hosts":[{"address":"10.0.0.1","change":"changed","hostnames":[],"#,
+                r#""ports":[{"port":80,"protocol":"tcp","change":"changed","#,
+                r#""old":{"name":"http","service":"http","keys":[]},"#,
+                r#""new":{"name":"https","service":"https","keys":[]}}],"#,
+                r#""out_of_scope":false}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn unchanged_scans_have_no_changes() {
+        let ports = port(22, "open", r#"<service name="ssh" product="OpenSSH"/>"#);
+        let changes = ScanDiff::compare(&scan(&ports), &scan(&ports));
+
+        assert_eq!(changes.hosts[0].change, Change::Unchanged);
+        assert_eq!(changes.changelog(), "");
+        assert_eq!(changes.json(), r#"{"hosts":[]}"#);
+    }
+
+    #[test]
+    fn versions_and_new_hosts_are_described() {
+        let old = scan(&port(
+            80,
+            "open",
+            r#"<service name="http" product="nginx" version="1.18"/>"#,
+        ));
+        let mut new = scan(&port(
+            80,
+            "open",
+            r#"<service name="http" product="nginx" version="1.20"/>"#,
+        ));
+        let mut added = scan(&port(443, "open", ""));
+        added[0].addresses[0].address = "10.0.0.2".to_string();
+        new.append(&mut added);
+
+        let changes = ScanDiff::compare(&old, &new);
+        assert_eq!(
+            changes.changelog(),
+            concat!(
+                "~ 10.0.0.1\n",
+                "  ~ 80/tcp: http nginx 1.18 -> http nginx 1.20\n",
+                "+ 10.0.0.2\n",
+                "  + 443/tcp unknown"
+            )
+        );
+    }
+}
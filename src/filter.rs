@@ -0,0 +1,564 @@
+use crate::parser;
+use crate::parser::Host;
+use std::net;
+
+/// Something of a host or port that can be compared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Ip,
+    Mac,
+    Hostname,
+    Os,
+    Hops,
+    Ports,
+    Port,
+    Protocol,
+    Service,
+    Product,
+    Version,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Field> {
+        match name {
+            "ip" => Some(Field::Ip),
+            "mac" => Some(Field::Mac),
+            "hostname" | "name" => Some(Field::Hostname),
+            "os" => Some(Field::Os),
+            "hops" => Some(Field::Hops),
+            "ports" => Some(Field::Ports),
+            "port" => Some(Field::Port),
+            "protocol" => Some(Field::Protocol),
+            "service" => Some(Field::Service),
+            "product" => Some(Field::Product),
+            "version" => Some(Field::Version),
+            _ => None,
+        }
+    }
+
+    /// Whether the field belongs to a single port rather than the host.
+    fn is_port_field(&self) -> bool {
+        matches!(
+            self,
+            Field::Port | Field::Protocol | Field::Service | Field::Product | Field::Version
+        )
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Field::Hops | Field::Ports | Field::Port)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Case insensitive substring.
+    Match,
+    /// Case sensitive substring.
+    Contains,
+    /// Address within a network.
+    In,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(u64),
+    Text(String),
+    Network(net::IpAddr, u8),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Op(Op),
+    Word(String),
+    Quoted(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => text.push(c),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+            }
+            '=' | '!' | '<' | '>' | '~' | '&' | '|' => {
+                let mut op = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !"=!<>~&|".contains(c) {
+                        break;
+                    }
+                    op.push(c);
+                    chars.next();
+                }
+                tokens.push(match op.as_str() {
+                    "==" | "=" => Token::Op(Op::Eq),
+                    "!=" => Token::Op(Op::Ne),
+                    "<" => Token::Op(Op::Lt),
+                    "<=" => Token::Op(Op::Le),
+                    ">" => Token::Op(Op::Gt),
+                    ">=" => Token::Op(Op::Ge),
+                    "~" => Token::Op(Op::Match),
+                    "&&" => Token::Word("and".to_string()),
+                    "||" => Token::Word("or".to_string()),
+                    "!" => Token::Word("not".to_string()),
+                    op => return Err(format!("unknown operator \"{}\"", op)),
+                });
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()\"=!<>~&|".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(match word.as_str() {
+                    "contains" => Token::Op(Op::Contains),
+                    "in" => Token::Op(Op::In),
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser, `or` binds weaker than `and`, which binds weaker
+/// than `not`.
+struct ExprParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if self.peek() == Some(&Token::Word(keyword.to_string())) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            let expr = self.or()?;
+            return match self.next() {
+                Some(Token::Close) => Ok(expr),
+                _ => Err("missing \")\"".to_string()),
+            };
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let field = match self.next() {
+            Some(Token::Word(name)) => {
+                Field::parse(&name).ok_or(format!("unknown field \"{}\"", name))?
+            }
+            token => return Err(format!("expected a field, found {:?}", token)),
+        };
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            token => return Err(format!("expected an operator, found {:?}", token)),
+        };
+        let value = match self.next() {
+            Some(Token::Word(value)) | Some(Token::Quoted(value)) => value,
+            token => return Err(format!("expected a value, found {:?}", token)),
+        };
+
+        let value = match (field, op) {
+            (Field::Ip, Op::In) => {
+                let (address, prefix) = network(&value)?;
+                Value::Network(address, prefix)
+            }
+            (_, Op::In) => return Err("\"in\" compares ip addresses only".to_string()),
+            (field, _) if field.is_numeric() => Value::Number(
+                value
+                    .parse()
+                    .map_err(|_| format!("\"{}\" is not a number", value))?,
+            ),
+            (_, Op::Lt | Op::Le | Op::Gt | Op::Ge) => {
+                return Err("only numbers can be ordered".to_string())
+            }
+            _ => Value::Text(value),
+        };
+        if matches!(value, Value::Number(_)) && matches!(op, Op::Match | Op::Contains) {
+            return Err("numbers cannot be searched".to_string());
+        }
+
+        Ok(Expr::Compare(field, op, value))
+    }
+}
+
+/// Parses `10.0.0.0/8`, a single address is a network of its own.
+pub(crate) fn network(value: &str) -> Result<(net::IpAddr, u8), String> {
+    let (address, prefix) = value.split_once('/').unwrap_or((value, ""));
+    let address: net::IpAddr = address
+        .parse()
+        .map_err(|_| format!("\"{}\" is not an address", address))?;
+    let bits = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        "" => bits,
+        prefix => prefix
+            .parse()
+            .ok()
+            .filter(|p| *p <= bits)
+            .ok_or(format!("\"{}\" is not a prefix length", prefix))?,
+    };
+    Ok((address, prefix))
+}
+
+pub(crate) fn in_network(address: &str, network: &net::IpAddr, prefix: u8) -> bool {
+    match (address.parse::<net::IpAddr>(), network) {
+        (Ok(net::IpAddr::V4(address)), net::IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(address) & mask == u32::from(*network) & mask
+        }
+        (Ok(net::IpAddr::V6(address)), net::IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(address) & mask == u128::from(*network) & mask
+        }
+        _ => false,
+    }
+}
+
+fn compare_number(actual: u64, op: Op, expected: u64) -> bool {
+    match op {
+        Op::Eq => actual == expected,
+        Op::Ne => actual != expected,
+        Op::Lt => actual < expected,
+        Op::Le => actual <= expected,
+        Op::Gt => actual > expected,
+        Op::Ge => actual >= expected,
+        _ => false,
+    }
+}
+
+fn compare_text(actual: &str, op: Op, expected: &Value) -> bool {
+    match (op, expected) {
+        (Op::Eq, Value::Text(expected)) => actual.eq_ignore_ascii_case(expected),
+        (Op::Match, Value::Text(expected)) => {
+            actual.to_lowercase().contains(&expected.to_lowercase())
+        }
+        (Op::Contains, Value::Text(expected)) => actual.contains(expected.as_str()),
+        (Op::In, Value::Network(network, prefix)) => in_network(actual, network, *prefix),
+        _ => false,
+    }
+}
+
+/// A parsed filter expression like `port == 445 and os contains "Windows"`.
+///
+/// Comparisons of host fields (`ip`, `mac`, `hostname`, `os`, `hops` and the
+/// number of `ports`) hold for all ports of a host, comparisons of port fields
+/// (`port`, `protocol`, `service`, `product` and `version`) for single ports.
+/// Matched against a host, a comparison of a port field holds if any port of
+/// the host matches, so `port == 22 and port == 80` selects hosts with both
+/// ports and `not port == 22` hosts without port 22. Fields with several
+/// values, like the addresses of a host, match if any of them does. `!=` is
+/// the negation of `==`.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    pub fn parse(input: &str) -> Result<Filter, String> {
+        let mut parser = ExprParser {
+            tokens: tokenize(input)?,
+            position: 0,
+        };
+        let expr = parser.or()?;
+        match parser.next() {
+            None => Ok(Filter { expr }),
+            Some(token) => Err(format!("unexpected {:?}", token)),
+        }
+    }
+
+    /// Whether the host matches, each comparison of a port field is checked
+    /// against all of its ports.
+    pub fn host(&self, host: &Host) -> bool {
+        Filter::eval(&self.expr, host, None)
+    }
+
+    pub fn port(&self, host: &Host, port: &parser::Port) -> bool {
+        Filter::eval(&self.expr, host, Some(port))
+    }
+
+    fn eval(expr: &Expr, host: &Host, port: Option<&parser::Port>) -> bool {
+        match expr {
+            Expr::And(a, b) => Filter::eval(a, host, port) && Filter::eval(b, host, port),
+            Expr::Or(a, b) => Filter::eval(a, host, port) || Filter::eval(b, host, port),
+            Expr::Not(a) => !Filter::eval(a, host, port),
+            Expr::Compare(field, Op::Ne, value) => {
+                !Filter::compare(*field, Op::Eq, value, host, port)
+            }
+            Expr::Compare(field, op, value) => Filter::compare(*field, *op, value, host, port),
+        }
+    }
+
+    fn compare(
+        field: Field,
+        op: Op,
+        value: &Value,
+        host: &Host,
+        port: Option<&parser::Port>,
+    ) -> bool {
+        if field.is_port_field() && port.is_none() {
+            return host
+                .ports
+                .iter()
+                .any(|p| Filter::compare(field, op, value, host, Some(p)));
+        }
+
+        if let Value::Number(expected) = value {
+            let actual = match field {
+                Field::Hops => Some(host.hops.len() as u64),
+                Field::Ports => Some(host.ports.len() as u64),
+                Field::Port => port.map(|p| p.port as u64),
+                _ => None,
+            };
+            return actual.is_some_and(|actual| compare_number(actual, op, *expected));
+        }
+
+        let service = port.and_then(|p| p.service.as_ref());
+        let values: Vec<&str> = match field {
+            Field::Ip | Field::Mac => host
+                .addresses
+                .iter()
+                .filter(|a| matches!(a.addr_type, parser::AddrType::MAC) == (field == Field::Mac))
+                .map(|a| a.address.as_str())
+                .collect(),
+            Field::Hostname => host.hostnames.iter().map(|h| h.as_str()).collect(),
+            Field::Os => host
+                .os
+                .iter()
+                .chain(host.os_classes.iter().filter_map(|c| c.family.as_ref()))
+                .map(|os| os.as_str())
+                .collect(),
+            Field::Protocol => port.map(|p| p.protocol.as_str()).into_iter().collect(),
+            Field::Service => service
+                .and_then(|s| s.name.as_deref())
+                .into_iter()
+                .collect(),
+            Field::Product => service
+                .and_then(|s| s.product.as_deref())
+                .into_iter()
+                .collect(),
+            Field::Version => service
+                .and_then(|s| s.version.as_deref())
+                .into_iter()
+                .collect(),
+            Field::Hops | Field::Ports | Field::Port => Vec::new(),
+        };
+        values.iter().any(|actual| compare_text(actual, op, value))
+    }
+}
+
+/// Keeps the hosts matching `hosts` and, of those, the ports matching `ports`.
+pub fn apply(hosts: Vec<Host>, filter: Option<&Filter>, ports: Option<&Filter>) -> Vec<Host> {
+    hosts
+        .into_iter()
+        .filter(|host| filter.is_none_or(|f| f.host(host)))
+        .map(|mut host| {
+            if let Some(ports) = ports {
+                let kept = host
+                    .ports
+                    .iter()
+                    .filter(|p| ports.port(&host, p))
+                    .cloned()
+                    .collect();
+                host.ports = kept;
+            }
+            host
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(address: &str, ports: &[(u16, &str)]) -> Host {
+        Host {
+            addresses: vec![parser::IpAddr {
+                address: address.to_string(),
+                addr_type: match address.contains(':') {
+                    true => parser::AddrType::IPv6,
+                    false => parser::AddrType::IPv4,
+                },
+            }],
+            os: Some("Microsoft Windows Server 2019".to_string()),
+            ports: ports
+                .iter()
+                .map(|(port, product)| parser::Port {
+                    protocol: "tcp".to_string(),
+                    port: *port,
+                    service: Some(parser::Service {
+                        name: None,
+                        product: Some(product.to_string()),
+                        version: None,
+                        extrainfo: None,
+                    }),
+                    metadata: parser::Metadata::None,
+                    findings: Vec::new(),
+                })
+                .collect(),
+            ..Host::default()
+        }
+    }
+
+    fn matches(filter: &str, host: &Host) -> bool {
+        Filter::parse(filter).unwrap().host(host)
+    }
+
+    fn error(filter: &str) -> String {
+        Filter::parse(filter).unwrap_err()
+    }
+
+    #[test]
+    fn and_binds_stronger_than_or() {
+        let ssh = host("10.0.0.1", &[(22, "OpenSSH")]);
+        let web = host("10.0.0.2", &[(80, "nginx")]);
+
+        assert!(matches("port == 22 or port == 80 and port == 443", &ssh));
+        assert!(!matches("port == 22 or port == 80 and port == 443", &web));
+        assert!(matches("(port == 22 or port == 80) and ports == 1", &web));
+        assert!(matches("not port == 22 and port == 80", &web));
+        assert!(!matches("not (port == 22 or port == 80)", &web));
+    }
+
+    #[test]
+    fn port_fields_match_any_port_of_a_host() {
+        let both = host("10.0.0.1", &[(22, "OpenSSH"), (80, "nginx")]);
+        let ssh = host("10.0.0.2", &[(22, "OpenSSH")]);
+        let none = host("10.0.0.3", &[]);
+
+        assert!(matches("port == 22 and port == 80", &both));
+        assert!(!matches("port == 22 and port == 80", &ssh));
+        assert!(!matches("not port == 22", &both));
+        assert!(!matches("port != 22", &both));
+        assert!(matches("not port == 22", &none));
+        assert!(!matches("port == 22", &none));
+    }
+
+    #[test]
+    fn port_filter_checks_single_ports() {
+        let filter = Filter::parse("not port == 22").unwrap();
+        let host = host("10.0.0.1", &[(22, "OpenSSH"), (80, "nginx")]);
+
+        assert!(!filter.port(&host, &host.ports[0]));
+        assert!(filter.port(&host, &host.ports[1]));
+    }
+
+    #[test]
+    fn quoted_values() {
+        let host = host("10.0.0.1", &[(80, "Apache httpd")]);
+
+        assert!(matches("product == \"apache httpd\"", &host));
+        assert!(matches("os contains \"Windows Server\"", &host));
+        assert!(!matches("os contains \"windows server\"", &host));
+        assert!(matches("os ~ \"windows server\"", &host));
+    }
+
+    #[test]
+    fn ip_in_network() {
+        let v4 = host("10.1.2.3", &[]);
+        let v6 = host("2001:db8::1", &[]);
+
+        assert!(matches("ip in 10.0.0.0/8", &v4));
+        assert!(!matches("ip in 10.0.0.0/16", &v4));
+        assert!(matches("ip in 10.1.2.3", &v4));
+        assert!(matches("ip in 2001:db8::/32", &v6));
+        assert!(!matches("ip in 10.0.0.0/8", &v6));
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(error("color == red"), "unknown field \"color\"");
+        assert_eq!(error("port == ssh"), "\"ssh\" is not a number");
+        assert_eq!(error("port ~ 22"), "numbers cannot be searched");
+        assert_eq!(error("service > ssh"), "only numbers can be ordered");
+        assert_eq!(
+            error("hostname in 10.0.0.0/8"),
+            "\"in\" compares ip addresses only"
+        );
+        assert_eq!(error("ip in 10.0.0.0/33"), "\"33\" is not a prefix length");
+        assert_eq!(
+            error("ip in example.com"),
+            "\"example.com\" is not an address"
+        );
+        assert_eq!(error("os == \"Windows"), "unterminated string");
+        assert_eq!(error("(port == 22"), "missing \")\"");
+        assert_eq!(error("port => 22"), "unknown operator \"=>\"");
+        assert_eq!(error("port == 22 22"), "unexpected Word(\"22\")");
+        assert_eq!(
+            error("port 22"),
+            "expected an operator, found Some(Word(\"22\"))"
+        );
+    }
+}
@@ -0,0 +1,53 @@
+/// Advance widths of the printable ASCII characters (space to `~`) in
+/// Helvetica, the default font of drawio, in 1/1000 of the font size.
+#[rustfmt::skip]
+static HELVETICA: [u64; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // space - /
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, // 0 - ?
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, // @ - O
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, // P - _
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, // ` - o
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // p - ~
+];
+
+/// Width used for characters missing from the table.
+static DEFAULT_WIDTH: u64 = 556;
+
+/// Horizontal space drawio keeps between the text and the cell border.
+pub static CELL_SPACING: u64 = 8;
+
+static ELLIPSIS: &str = "...";
+
+fn char_width(c: char) -> u64 {
+    match c {
+        ' '..='~' => HELVETICA[c as usize - ' ' as usize],
+        _ => DEFAULT_WIDTH,
+    }
+}
+
+/// Estimates the rendered width of `text` in pixels.
+pub fn width(text: &str, size: u64) -> u64 {
+    let width: u64 = text.chars().map(char_width).sum();
+    (width * size).div_ceil(1000)
+}
+
+/// Width of a cell that fits `text` without wrapping.
+pub fn cell_width(text: &str, size: u64) -> u64 {
+    width(text, size) + CELL_SPACING
+}
+
+/// Shortens `text` so its cell is at most `max_width` pixels wide, marking
+/// the cut with an ellipsis.
+pub fn truncate(text: &str, size: u64, max_width: u64) -> String {
+    if cell_width(text, size) <= max_width {
+        return text.to_string();
+    }
+
+    let mut truncated = text.to_string();
+    while !truncated.is_empty()
+        && cell_width(&format!("{}{}", truncated, ELLIPSIS), size) > max_width
+    {
+        truncated.pop();
+    }
+    format!("{}{}", truncated, ELLIPSIS)
+}
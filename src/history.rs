@@ -0,0 +1,325 @@
+use crate::diff;
+use crate::parser;
+use crate::parser::Host;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::path::PathBuf;
+
+pub static DEFAULT_STORE: &str = "./.networkplan-history";
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub start: u64,
+    pub path: PathBuf,
+}
+
+impl Snapshot {
+    pub fn parse(&self) -> Result<(parser::ScanInfo, Vec<Host>), String> {
+        parser::Parser::parse_with_info(self.path.to_string_lossy().to_string())
+    }
+
+    pub fn hosts(&self) -> Result<Vec<Host>, String> {
+        Ok(self.parse()?.1)
+    }
+}
+
+/// File-based store of nmap runs. Every ingested scan is kept as
+/// `<start>.xml`, where `<start>` is the unix timestamp of `<nmaprun start=...>`.
+pub struct History {
+    directory: PathBuf,
+}
+
+impl History {
+    pub fn open(directory: String) -> Result<History, String> {
+        fs::create_dir_all(&directory).map_err(|e| format!("{}: {}", directory, e))?;
+        Ok(History {
+            directory: PathBuf::from(directory),
+        })
+    }
+
+    /// Copies an nmap xml scan into the store and returns its start time.
+    /// Compressed scans are stored decompressed, `-` reads the scan from
+    /// stdin. Other files and scans started at the time of a stored one are
+    /// rejected.
+    pub fn ingest(&self, filename: String) -> Result<u64, String> {
+        let mut scan = Vec::new();
+        let input: Box<dyn Read> = match filename.as_str() {
+            "-" => Box::new(io::stdin()),
+            _ => Box::new(fs::File::open(&filename).map_err(|e| e.to_string())?),
+        };
+        parser::Parser::input(input)
+            .and_then(|mut input| input.read_to_end(&mut scan))
+            .map_err(|e| e.to_string())?;
+
+        // only the metadata is needed, so the hosts are dropped as they are parsed
+        let mut hosts = parser::Parser::hosts(&scan[..]);
+        for host in hosts.by_ref() {
+            host.map_err(|e| format!("not an nmap xml scan: {}", e))?;
+        }
+        let info = hosts.info();
+        if info.scanner != "nmap" {
+            return Err(match info.scanner.as_str() {
+                "" => "not an nmap xml scan, <nmaprun> is missing".to_string(),
+                scanner => format!("not an nmap xml scan but a {} scan", scanner),
+            });
+        }
+        let start = info.start;
+        if start == 0 {
+            return Err("not an nmap xml scan, <nmaprun start> is missing".to_string());
+        }
+
+        let path = self.directory.join(format!("{}.xml", start));
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::AlreadyExists => {
+                    format!("a scan started at {} is already stored", format_date(start))
+                }
+                _ => e.to_string(),
+            })?;
+        file.write_all(&scan).map_err(|e| e.to_string())?;
+
+        Ok(start)
+    }
+
+    /// Returns all stored snapshots, oldest first.
+    pub fn snapshots(&self) -> Result<Vec<Snapshot>, String> {
+        let mut snapshots: Vec<Snapshot> = fs::read_dir(&self.directory)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let start = path.file_stem()?.to_str()?.parse().ok()?;
+                match path.extension()?.to_str()? {
+                    "xml" => Some(Snapshot { start, path }),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        snapshots.sort_by_key(|s| s.start);
+        Ok(snapshots)
+    }
+
+    /// Returns the latest snapshot started at or before `time`.
+    pub fn as_of(&self, time: u64) -> Result<Option<Snapshot>, String> {
+        Ok(self
+            .snapshots()?
+            .into_iter()
+            .rev()
+            .find(|s| s.start <= time))
+    }
+
+    /// Lists the changes of every host between consecutive snapshots. The
+    /// first snapshot a host appears in is reported as an addition.
+    pub fn timeline(&self, address: Option<&str>) -> Result<String, String> {
+        let mut hosts: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut previous: Vec<Host> = Vec::new();
+
+        for snapshot in self.snapshots()? {
+            let current = snapshot.hosts()?;
+            let changes = diff::ScanDiff::compare(&previous, &current);
+
+            for host in changes.changed_hosts() {
+                if address.is_some_and(|a| a != host.address) {
+                    continue;
+                }
+
+                let lines = hosts.entry(host.label()).or_default();
+                lines.push(format!(
+                    "  {}  {}",
+                    format_date(snapshot.start),
+                    host.change.name()
+                ));
+                for port in host
+                    .ports
+                    .iter()
+                    .filter(|p| p.change != diff::Change::Unchanged)
+                {
+                    lines.push(format!(
+                        "  {}    {}",
+                        format_date(snapshot.start),
+                        port.describe()
+                    ));
+                }
+            }
+
+            previous = current;
+        }
+
+        let mut output = Vec::new();
+        for (host, lines) in hosts {
+            output.push(host);
+            output.extend(lines);
+        }
+        Ok(output.join("\n"))
+    }
+}
+
+/// Parses either a unix timestamp or a `YYYY-MM-DD` date (end of that day, UTC).
+pub fn parse_date(date: &str) -> Option<u64> {
+    if let Ok(timestamp) = date.parse() {
+        return Some(timestamp);
+    }
+
+    let parts: Vec<i64> = date
+        .split('-')
+        .map(|p| p.parse().ok())
+        .collect::<Option<Vec<i64>>>()?;
+    let (year, month, day) = match parts[..] {
+        [year, month, day] => (year, month, day),
+        _ => return None,
+    };
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    u64::try_from(days * 86400 + 86399).ok()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD HH:MM` (UTC).
+pub fn format_date(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        (seconds % 3600) / 60
+    )
+}
+
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A history in a fresh temporary directory.
+    fn history(name: &str) -> History {
+        let directory = std::env::temp_dir().join(format!(
+            "networkplan-history-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        History::open(directory.to_string_lossy().to_string()).unwrap()
+    }
+
+    fn ingest(history: &History, scanner: &str, start: u64, ports: &[u16]) -> Result<u64, String> {
+        let ports: String = ports
+            .iter()
+            .map(|p| {
+                format!(
+                    r#"<port protocol="tcp" portid="{}"><state state="open"/></port>"#,
+                    p
+                )
+            })
+            .collect();
+        let scan = format!(
+            r#"<nmaprun scanner="{}" start="{}"><host><address addr="10.0.0.1" addrtype="ipv4"/><ports>{}</ports></host></nmaprun>"#,
+            scanner, start, ports
+        );
+        let path = history.directory.join("scan.tmp");
+        fs::write(&path, scan).unwrap();
+        history.ingest(path.to_string_lossy().to_string())
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date("1700000000"), Some(1700000000));
+        assert_eq!(parse_date("2023-11-14"), Some(1700006399));
+        assert_eq!(parse_date("2024-02-29"), Some(1709251199));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2023-13-01"), None);
+        assert_eq!(parse_date("2023-11"), None);
+        assert_eq!(parse_date("yesterday"), None);
+
+        assert_eq!(format_date(1700000000), "2023-11-14 22:13");
+        assert_eq!(format_date(0), "1970-01-01 00:00");
+    }
+
+    #[test]
+    fn only_new_nmap_scans_are_stored() {
+        let history = history("ingest");
+
+        assert_eq!(ingest(&history, "nmap", 1700000000, &[22]), Ok(1700000000));
+        assert!(ingest(&history, "nmap", 1700000000, &[80])
+            .unwrap_err()
+            .contains("already stored"));
+        assert!(ingest(&history, "masscan", 1700086400, &[22])
+            .unwrap_err()
+            .contains("masscan"));
+        assert!(ingest(&history, "nmap", 0, &[22]).is_err());
+        assert_eq!(history.snapshots().unwrap().len(), 1);
+
+        fs::remove_dir_all(&history.directory).unwrap();
+    }
+
+    #[test]
+    fn timeline() {
+        let history = history("timeline");
+        ingest(&history, "nmap", 1700000000, &[22]).unwrap();
+        ingest(&history, "nmap", 1700086400, &[22]).unwrap();
+        ingest(&history, "nmap", 1700172800, &[22, 80]).unwrap();
+
+        assert_eq!(
+            history.timeline(None).unwrap(),
+            concat!(
+                "10.0.0.1\n",
+                "  2023-11-14 22:13  added\n",
+                "  2023-11-14 22:13    + 22/tcp unknown\n",
+                "  2023-11-16 22:13  changed\n",
+                "  2023-11-16 22:13    + 80/tcp unknown"
+            )
+        );
+        assert_eq!(history.timeline(Some("10.0.0.2")).unwrap(), "");
+        assert_eq!(
+            history.as_of(1700100000).unwrap().map(|s| s.start),
+            Some(1700086400)
+        );
+        assert!(history.as_of(1600000000).unwrap().is_none());
+
+        fs::remove_dir_all(&history.directory).unwrap();
+    }
+}
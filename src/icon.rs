@@ -0,0 +1,89 @@
+use crate::parser;
+
+/// Kind of device a host is drawn as, shown as an icon in the server box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Device {
+    Server,
+    Workstation,
+    Router,
+    Switch,
+    Firewall,
+    AccessPoint,
+    Printer,
+    Phone,
+    Storage,
+}
+
+/// Ports of print servers: jetdirect, lpd and ipp.
+static PRINTER_PORTS: [u16; 3] = [9100, 515, 631];
+
+impl Device {
+    /// Guesses the device from the OS detection of nmap, falling back to the
+    /// open ports and the OS family.
+    pub fn detect(host: &parser::Host) -> Device {
+        let os_class = host.os_classes.first();
+
+        let by_type = os_class
+            .and_then(|c| c.device_type.as_deref())
+            .and_then(Device::from_type);
+        if let Some(device) = by_type {
+            return device;
+        }
+
+        let open = |port: u16| host.ports.iter().any(|p| p.port == port);
+        if PRINTER_PORTS.iter().any(|p| open(*p)) {
+            return Device::Printer;
+        }
+        if open(161) && open(23) {
+            return Device::Switch;
+        }
+        if open(5060) && host.ports.len() <= 3 {
+            return Device::Phone;
+        }
+
+        let family = os_class
+            .and_then(|c| c.family.clone())
+            .or_else(|| host.os.clone())
+            .unwrap_or_default();
+        match family.as_str() {
+            "IOS" | "IOS XE" | "JunOS" | "RouterOS" => Device::Router,
+            "PAN-OS" | "FortiOS" | "ASA" => Device::Firewall,
+            "Android" | "iOS" => Device::Phone,
+            "Mac OS X" | "macOS" => Device::Workstation,
+            _ => Device::Server,
+        }
+    }
+
+    /// Maps the `type` of an `<osclass>` to a device.
+    fn from_type(device_type: &str) -> Option<Device> {
+        match device_type {
+            "router" | "broadband router" => Some(Device::Router),
+            "switch" => Some(Device::Switch),
+            "firewall" => Some(Device::Firewall),
+            "WAP" => Some(Device::AccessPoint),
+            "printer" | "print server" => Some(Device::Printer),
+            "phone" | "VoIP phone" | "VoIP adapter" => Some(Device::Phone),
+            "storage-misc" => Some(Device::Storage),
+            _ => None,
+        }
+    }
+
+    /// Drawio style of the icon, using the Cisco shape library.
+    pub fn style(&self) -> String {
+        let shape = match self {
+            Device::Server => "servers.fileserver",
+            Device::Workstation => "computers_and_peripherals.pc",
+            Device::Router => "routers.router",
+            Device::Switch => "switches.workgroup_switch",
+            Device::Firewall => "security.firewall",
+            Device::AccessPoint => "wireless.access_point",
+            Device::Printer => "computers_and_peripherals.printer",
+            Device::Phone => "modems_and_phones.ip_phone",
+            Device::Storage => "storage.cisco_file_engine",
+        };
+        format!(
+            "shape=mxgraph.cisco.{};html=1;pointerEvents=1;dashed=0;fillColor=#036897;strokeColor=#ffffff;strokeWidth=2;verticalLabelPosition=bottom;verticalAlign=top;align=center;outlineConnect=0;",
+            shape
+        )
+    }
+}
@@ -0,0 +1,431 @@
+//! Adapters reading the output of other scanners into the `parser::Host`
+//! model, so it can be rendered like an nmap scan.
+
+use crate::parser;
+use crate::parser::Host;
+use crate::parser::Parser;
+use crate::parser::ScanInfo;
+use quick_xml::events::BytesText;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::Read;
+use std::str;
+
+pub mod grepable;
+pub mod masscan;
+pub mod nessus;
+pub mod normal;
+pub mod openvas;
+pub mod plain;
+
+/// Formats `read` understands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Nmap,
+    NmapGrepable,
+    NmapNormal,
+    Masscan,
+    MasscanJson,
+    Nessus,
+    OpenVAS,
+    PortList,
+    PortListJson,
+}
+
+/// Guesses the format from the beginning of the input.
+pub fn detect(head: &[u8]) -> Format {
+    let head = String::from_utf8_lossy(head);
+    let trimmed = head.trim_start();
+
+    if trimmed.starts_with('<') {
+        if head.contains("scanner=\"masscan\"") {
+            Format::Masscan
+        } else if head.contains("<NessusClientData") {
+            Format::Nessus
+        } else if head.contains("<report") && !head.contains("<nmaprun") {
+            Format::OpenVAS
+        } else {
+            Format::Nmap
+        }
+    } else if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        // masscan groups the ports of a record, naabu and zmap write one each
+        match head.contains("\"ports\"") {
+            true => Format::MasscanJson,
+            false => Format::PortListJson,
+        }
+    } else if head.lines().any(|l| l.starts_with("Host: ")) {
+        Format::NmapGrepable
+    } else if trimmed.starts_with("# Nmap") || head.contains("Nmap scan report for") {
+        Format::NmapNormal
+    } else if head.lines().any(|l| plain::entry(l).is_some()) {
+        Format::PortList
+    } else {
+        Format::Nmap
+    }
+}
+
+/// Reads a scan in any supported format from the given file, or stdin if the
+/// filename is `-`. Gzip compressed input is decompressed. Errors name the
+/// file they occurred in.
+pub fn read(filename: String) -> Result<(ScanInfo, Vec<Host>), String> {
+    let scan = match filename.as_str() {
+        "-" => read_reader(io::stdin()),
+        _ => File::open(&filename)
+            .map_err(|e| e.to_string())
+            .and_then(read_reader),
+    };
+    scan.map_err(|e| format!("{}: {}", filename, e))
+}
+
+pub fn read_reader<'a, R: Read + 'a>(reader: R) -> Result<(ScanInfo, Vec<Host>), String> {
+    let mut input = Parser::input(reader).map_err(|e| e.to_string())?;
+    let format = detect(input.fill_buf().map_err(|e| e.to_string())?);
+
+    match format {
+        Format::Nmap => Parser::parse_reader(input),
+        Format::NmapGrepable => grepable::read(input),
+        Format::NmapNormal => normal::read(input),
+        Format::Masscan => masscan::xml(input),
+        Format::MasscanJson => masscan::json(input),
+        Format::Nessus => nessus::read(input),
+        Format::OpenVAS => openvas::read(input),
+        Format::PortList => plain::text(input),
+        Format::PortListJson => plain::json(input),
+    }
+}
+
+/// Reads several scans and merges their hosts, e.g. an nmap scan and a
+/// vulnerability report of the same network. The metadata of the first scan
+/// is kept.
+pub fn read_all(filenames: &[String]) -> Result<(ScanInfo, Vec<Host>), String> {
+    let mut scans = filenames.iter().map(|f| read(f.to_string()));
+    let (info, mut hosts) = scans.next().transpose()?.unwrap_or_default();
+    for scan in scans {
+        hosts.extend(scan?.1);
+    }
+
+    match filenames.len() {
+        1 => Ok((info, hosts)),
+        _ => Ok((info, merge(hosts))),
+    }
+}
+
+/// Text of an element with its entities resolved.
+fn text(e: &BytesText) -> Result<String, String> {
+    let text = e.unescaped().map_err(|e| e.to_string())?;
+    Ok(str::from_utf8(&text)
+        .map_err(|e| e.to_string())?
+        .to_string())
+}
+
+/// Reads the comments nmap writes at the start and the end of its grepable and
+/// normal output:
+///
+/// ```text
+/// # Nmap 7.94 scan initiated Tue Nov 14 22:13:20 2023 as: nmap -oG out.gnmap 10.0.0.0/24
+/// # Nmap done at Tue Nov 14 22:16:40 2023 -- 256 IP addresses (3 hosts up) scanned in 200.00 seconds
+/// ```
+fn nmap_comment(line: &str, info: &mut ScanInfo) {
+    if let Some(started) = line.strip_prefix("# Nmap ") {
+        if let Some((version, rest)) = started.split_once(" scan initiated ") {
+            let (startstr, args) = rest.split_once(" as: ").unwrap_or((rest, ""));
+            info.scanner = "nmap".to_string();
+            info.version = version.to_string();
+            info.startstr = startstr.to_string();
+            info.args = args.to_string();
+        }
+    }
+
+    if let Some(done) = line.strip_prefix("# Nmap done at ") {
+        let (endstr, summary) = done.split_once(" -- ").unwrap_or((done, ""));
+        info.endstr = Some(endstr.to_string());
+        info.summary = Some(summary.to_string());
+
+        // "256 IP addresses (3 hosts up) scanned in 200.00 seconds"
+        let mut words = summary.split_whitespace();
+        info.hosts_total = words.next().and_then(|w| w.parse().ok()).unwrap_or(0);
+        info.hosts_up = summary
+            .split_once('(')
+            .and_then(|(_, up)| up.split_whitespace().next())
+            .and_then(|w| w.parse().ok())
+            .unwrap_or(0);
+        info.hosts_down = info.hosts_total.saturating_sub(info.hosts_up);
+    }
+}
+
+/// Splits nmap's version column, e.g. "OpenSSH 7.4 (protocol 2.0)", into the
+/// product and the extra info. Product and version cannot be told apart, so
+/// the product keeps both.
+fn version_column(column: &str) -> (Option<String>, Option<String>) {
+    let column = column.trim();
+    if column.is_empty() {
+        return (None, None);
+    }
+
+    match column.strip_suffix(')').and_then(|c| c.rsplit_once(" (")) {
+        Some((product, extrainfo)) => (Some(product.to_string()), Some(extrainfo.to_string())),
+        None => (Some(column.to_string()), None),
+    }
+}
+
+/// Merges records of the same host, as scanners like masscan report every
+/// port separately, or to combine the scans of several tools. Ports are merged
+/// by number and protocol, the first service found for a port wins.
+pub fn merge(hosts: Vec<Host>) -> Vec<Host> {
+    let mut merged: Vec<Host> = Vec::new();
+    // position of each host in `merged` by its key, and of its ports
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut port_index: Vec<HashMap<(u16, String), usize>> = Vec::new();
+
+    for host in hosts {
+        let key = host.key();
+        let position = match key.as_ref().and_then(|k| index.get(k)) {
+            Some(position) => *position,
+            None => {
+                if let Some(key) = key {
+                    index.insert(key, merged.len());
+                }
+                port_index.push(
+                    host.ports
+                        .iter()
+                        .enumerate()
+                        .map(|(i, p)| ((p.port, p.protocol.clone()), i))
+                        .collect(),
+                );
+                merged.push(host);
+                continue;
+            }
+        };
+        let existing = &mut merged[position];
+        let ports = &mut port_index[position];
+
+        for address in host.addresses {
+            if !existing
+                .addresses
+                .iter()
+                .any(|a| a.address == address.address)
+            {
+                existing.addresses.push(address);
+            }
+        }
+        for hostname in host.hostnames {
+            if !existing.hostnames.contains(&hostname) {
+                existing.hostnames.push(hostname);
+            }
+        }
+        for port in host.ports {
+            match ports.get(&(port.port, port.protocol.clone())) {
+                Some(i) => {
+                    let existing_port = &mut existing.ports[*i];
+                    if existing_port.service.is_none() {
+                        existing_port.service = port.service;
+                    }
+                    existing_port.findings.extend(port.findings);
+                }
+                None => {
+                    ports.insert((port.port, port.protocol.clone()), existing.ports.len());
+                    existing.ports.push(port);
+                }
+            }
+        }
+        if existing.os.is_none() {
+            existing.os = host.os;
+        }
+        if existing.os_classes.is_empty() {
+            existing.os_classes = host.os_classes;
+        }
+        if existing.hops.is_empty() {
+            existing.hops = host.hops;
+        }
+    }
+
+    for host in merged.iter_mut() {
+        host.ports.sort_by_key(|p| p.port);
+    }
+    merged
+}
+
+/// Finds a port of the host, adding it if it is not known yet.
+fn port_mut<'a>(host: &'a mut Host, port: u16, protocol: &str) -> &'a mut parser::Port {
+    let index = match host
+        .ports
+        .iter()
+        .position(|p| p.port == port && p.protocol == protocol)
+    {
+        Some(index) => index,
+        None => {
+            host.ports.push(parser::Port {
+                protocol: protocol.to_string(),
+                port,
+                service: None,
+                metadata: parser::Metadata::None,
+                findings: Vec::new(),
+            });
+            host.ports.len() - 1
+        }
+    };
+    &mut host.ports[index]
+}
+
+/// Address of a host, its type taken from the notation.
+fn address(address: &str) -> parser::IpAddr {
+    parser::IpAddr {
+        address: address.to_string(),
+        addr_type: match address.contains(':') {
+            true => parser::AddrType::IPv6,
+            false => parser::AddrType::IPv4,
+        },
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Describes each host on a line, e.g.
+    /// `10.0.0.1 (gw.example.com): 22/tcp ssh, 80/tcp`.
+    pub(crate) fn summary(hosts: &[Host]) -> Vec<String> {
+        hosts
+            .iter()
+            .map(|h| {
+                let mut names: Vec<String> =
+                    h.addresses.iter().map(|a| a.address.clone()).collect();
+                names.extend(h.hostnames.iter().map(|n| format!("({})", n)));
+                let ports: Vec<String> = h
+                    .ports
+                    .iter()
+                    .map(|p| match p.service.as_ref().and_then(|s| s.name.as_ref()) {
+                        Some(name) => format!("{}/{} {}", p.port, p.protocol, name),
+                        None => format!("{}/{}", p.port, p.protocol),
+                    })
+                    .collect();
+                format!("{}: {}", names.join(" "), ports.join(", "))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn formats_are_detected() {
+        let detected = |head: &str| detect(head.as_bytes());
+
+        assert_eq!(
+            detected("<?xml version=\"1.0\"?>\n<nmaprun scanner=\"nmap\">"),
+            Format::Nmap
+        );
+        assert_eq!(
+            detected("<?xml version=\"1.0\"?>\n<nmaprun scanner=\"masscan\">"),
+            Format::Masscan
+        );
+        assert_eq!(
+            detected("<?xml version=\"1.0\" ?>\n<NessusClientData_v2>"),
+            Format::Nessus
+        );
+        assert_eq!(
+            detected("<report id=\"1\" format_id=\"a994b278\">"),
+            Format::OpenVAS
+        );
+        assert_eq!(
+            detected("[\n{   \"ip\": \"10.0.0.1\", \"ports\": [ {\"port\": 80} ] }"),
+            Format::MasscanJson
+        );
+        assert_eq!(
+            detected("{\"ip\":\"10.0.0.1\",\"port\":443}"),
+            Format::PortListJson
+        );
+        assert_eq!(
+            detected("# Nmap 7.94 scan initiated Tue Nov 14 22:13:20 2023 as: nmap -oG -\nHost: 10.0.0.1 ()\tStatus: Up"),
+            Format::NmapGrepable
+        );
+        assert_eq!(
+            detected("# Nmap 7.94 scan initiated Tue Nov 14 22:13:20 2023 as: nmap 10.0.0.1\nNmap scan report for 10.0.0.1"),
+            Format::NmapNormal
+        );
+        assert_eq!(detected("10.0.0.1:22\n10.0.0.1:80\n"), Format::PortList);
+    }
+
+    #[test]
+    fn nmap_comments_fill_the_scan_info() {
+        let mut info = ScanInfo::default();
+        nmap_comment(
+            "# Nmap 7.94 scan initiated Tue Nov 14 22:13:20 2023 as: nmap -oG out.gnmap 10.0.0.0/24",
+            &mut info,
+        );
+        nmap_comment(
+            "# Nmap done at Tue Nov 14 22:16:40 2023 -- 256 IP addresses (3 hosts up) scanned in 200.00 seconds",
+            &mut info,
+        );
+
+        assert_eq!(info.scanner, "nmap");
+        assert_eq!(info.version, "7.94");
+        assert_eq!(info.startstr, "Tue Nov 14 22:13:20 2023");
+        assert_eq!(info.args, "nmap -oG out.gnmap 10.0.0.0/24");
+        assert_eq!(info.endstr.as_deref(), Some("Tue Nov 14 22:16:40 2023"));
+        assert_eq!(
+            (info.hosts_up, info.hosts_down, info.hosts_total),
+            (3, 253, 256)
+        );
+    }
+
+    #[test]
+    fn version_columns() {
+        assert_eq!(
+            version_column("OpenSSH 7.4 (protocol 2.0)"),
+            (
+                Some("OpenSSH 7.4".to_string()),
+                Some("protocol 2.0".to_string())
+            )
+        );
+        assert_eq!(
+            version_column("nginx 1.18.0"),
+            (Some("nginx 1.18.0".to_string()), None)
+        );
+        assert_eq!(version_column(" "), (None, None));
+    }
+
+    #[test]
+    fn records_of_a_host_are_merged() {
+        let host = |address: &str, hostname: &str, ports: &[(u16, Option<&str>)]| Host {
+            addresses: vec![super::address(address)],
+            hostnames: vec![hostname.to_string()],
+            ports: ports
+                .iter()
+                .map(|(port, name)| parser::Port {
+                    protocol: "tcp".to_string(),
+                    port: *port,
+                    service: name.map(|name| parser::Service {
+                        name: Some(name.to_string()),
+                        product: None,
+                        version: None,
+                        extrainfo: None,
+                    }),
+                    metadata: parser::Metadata::None,
+                    findings: Vec::new(),
+                })
+                .collect(),
+            ..Host::default()
+        };
+
+        let merged = merge(vec![
+            host("10.0.0.1", "gw", &[(80, None)]),
+            host("10.0.0.2", "db", &[(5432, Some("postgresql"))]),
+            host(
+                "10.0.0.1",
+                "gw.example.com",
+                &[(80, Some("http")), (22, None)],
+            ),
+            host("10.0.0.1", "gw", &[(80, Some("https"))]),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].hostnames, vec!["gw", "gw.example.com"]);
+        let ports: Vec<(u16, Option<String>)> = merged[0]
+            .ports
+            .iter()
+            .map(|p| (p.port, p.service.as_ref().and_then(|s| s.name.clone())))
+            .collect();
+        assert_eq!(ports, vec![(22, None), (80, Some("http".to_string()))]);
+        assert_eq!(merged[1].ports.len(), 1);
+    }
+}
@@ -0,0 +1,121 @@
+//! nmap's grepable output (`-oG`) has one line per host and kind of result,
+//! its fields are separated by tabs (shown as spaces):
+//!
+//! ```text
+//! Host: 10.0.0.1 (gw.example.com)    Status: Up
+//! Host: 10.0.0.1 (gw.example.com)    Ports: 22/open/tcp//ssh//OpenSSH 7.4 (protocol 2.0)/    Ignored State: closed (999)
+//! ```
+
+use crate::import;
+use crate::parser;
+use crate::parser::Host;
+use crate::parser::ScanInfo;
+use std::io::BufRead;
+
+pub fn read<R: BufRead>(input: R) -> Result<(ScanInfo, Vec<Host>), String> {
+    let mut info = ScanInfo::default();
+    let mut hosts = Vec::new();
+
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.starts_with('#') {
+            import::nmap_comment(&line, &mut info);
+            continue;
+        }
+
+        let mut fields = line.split('\t');
+        let host = match fields.next().and_then(|f| f.strip_prefix("Host: ")) {
+            Some(host) => host,
+            None => continue,
+        };
+        // hosts that did not answer are only listed with -Pn or -v
+        if line.split('\t').any(|f| f == "Status: Down") {
+            continue;
+        }
+
+        // "10.0.0.1 (gw.example.com)", the hostname may be empty
+        let (ip, hostname) = host.split_once(' ').unwrap_or((host, ""));
+        let hostname = hostname.trim_start_matches('(').trim_end_matches(')');
+
+        let mut current = Host {
+            addresses: vec![import::address(ip)],
+            ..Host::default()
+        };
+        if !hostname.is_empty() {
+            current.hostnames.push(hostname.to_string());
+        }
+
+        for field in fields {
+            if let Some(ports) = field.strip_prefix("Ports: ") {
+                current.ports = ports.split(", ").filter_map(port).collect();
+            } else if let Some(os) = field.strip_prefix("OS: ") {
+                current.os = Some(os.to_string());
+            }
+        }
+
+        hosts.push(current);
+    }
+
+    Ok((info, import::merge(hosts)))
+}
+
+/// Parses a port entry, `port/state/protocol/owner/service/rpc info/version/`.
+/// Ports that are not open are skipped.
+fn port(entry: &str) -> Option<parser::Port> {
+    let parts: Vec<&str> = entry.trim().split('/').collect();
+    if parts.len() < 7 || !parts[1].starts_with("open") {
+        return None;
+    }
+
+    let (product, extrainfo) = import::version_column(parts[6]);
+    let service = match parts[4] {
+        "" => None,
+        name => Some(parser::Service {
+            name: Some(name.to_string()),
+            product,
+            version: None,
+            extrainfo,
+        }),
+    };
+
+    Some(parser::Port {
+        protocol: parts[2].to_string(),
+        port: parts[0].parse().ok()?,
+        service,
+        metadata: parser::Metadata::None,
+        findings: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hosts_and_open_ports_are_read() {
+        let scan = "# Nmap 7.94 scan initiated Tue Nov 14 22:13:20 2023 as: nmap -oG out.gnmap -A 10.0.0.0/24
+Host: 10.0.0.1 (gw.example.com)\tStatus: Up
+Host: 10.0.0.1 (gw.example.com)\tPorts: 22/open/tcp//ssh//OpenSSH 7.4 (protocol 2.0)/, 23/open/tcp//telnet///, 81/closed/tcp//hosts2-ns///, 161/open|filtered/udp//snmp///\tIgnored State: closed (997)\tOS: Linux 4.15 - 5.8\tSeq Index: 260
+Host: 10.0.0.2 ()\tStatus: Down
+Host: 10.0.0.7 ()\tStatus: Up
+Host: 10.0.0.7 ()\tPorts: 445/open/tcp//microsoft-ds//Microsoft Windows Server 2008 R2 - 2012 microsoft-ds/
+# Nmap done at Tue Nov 14 22:16:40 2023 -- 256 IP addresses (2 hosts up) scanned in 200.00 seconds
+";
+        let (info, hosts) = read(scan.as_bytes()).unwrap();
+
+        assert_eq!(info.args, "nmap -oG out.gnmap -A 10.0.0.0/24");
+        assert_eq!(info.hosts_up, 2);
+        assert_eq!(
+            import::tests::summary(&hosts),
+            vec![
+                "10.0.0.1 (gw.example.com): 22/tcp ssh, 23/tcp telnet, 161/udp snmp",
+                "10.0.0.7: 445/tcp microsoft-ds",
+            ]
+        );
+        assert_eq!(hosts[0].os.as_deref(), Some("Linux 4.15 - 5.8"));
+
+        let ssh = hosts[0].ports[0].service.as_ref().unwrap();
+        assert_eq!(ssh.product.as_deref(), Some("OpenSSH 7.4"));
+        assert_eq!(ssh.extrainfo.as_deref(), Some("protocol 2.0"));
+    }
+}
@@ -0,0 +1,185 @@
+//! masscan writes one record per open port, as nmap-like xml (`-oX`) or as
+//! json (`-oJ`, `--output-format ndjson`). Service data is limited to the
+//! banners of `--banners`.
+
+use crate::history;
+use crate::import;
+use crate::parser;
+use crate::parser::Host;
+use crate::parser::Parser;
+use crate::parser::ScanInfo;
+use serde::Deserialize;
+use std::io::BufRead;
+
+/// Banner records that describe a port, not a service.
+static NO_SERVICE: [&str; 3] = ["title", "X509", "X509CERT"];
+
+#[derive(Debug, Deserialize)]
+struct Record {
+    ip: String,
+    timestamp: Option<serde_json::Value>,
+    #[serde(default)]
+    ports: Vec<RecordPort>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordPort {
+    port: u16,
+    proto: String,
+    service: Option<RecordService>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordService {
+    name: String,
+}
+
+fn service(name: &str) -> Option<parser::Service> {
+    match NO_SERVICE.contains(&name) {
+        true => None,
+        false => Some(parser::Service {
+            name: Some(name.to_string()),
+            product: None,
+            version: None,
+            extrainfo: None,
+        }),
+    }
+}
+
+/// Reads masscan's xml output. It is close enough to nmap's to use the nmap
+/// parser, only the per-port records have to be merged.
+pub fn xml<R: BufRead>(input: R) -> Result<(ScanInfo, Vec<Host>), String> {
+    let (mut info, mut hosts) = Parser::parse_reader(input)?;
+    if info.startstr.is_empty() && info.start != 0 {
+        info.startstr = history::format_date(info.start);
+    }
+    for host in hosts.iter_mut() {
+        for port in host.ports.iter_mut() {
+            let banner = port.service.as_ref().and_then(|s| s.name.clone());
+            port.service = banner.and_then(|name| service(&name));
+        }
+    }
+
+    Ok((info, import::merge(hosts)))
+}
+
+/// Reads masscan's json output. Its json is famously broken (trailing commas,
+/// a `{finished: 1}` record), but every record is on a line of its own, so
+/// the records are parsed line by line.
+pub fn json<R: BufRead>(input: R) -> Result<(ScanInfo, Vec<Host>), String> {
+    let mut start: Option<u64> = None;
+    let mut end: Option<u64> = None;
+    let mut hosts = Vec::new();
+
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let line = line.trim().trim_end_matches(',');
+        if !line.starts_with('{') || line.starts_with("{finished") {
+            continue;
+        }
+
+        let record: Record =
+            serde_json::from_str(line).map_err(|e| format!("invalid masscan record: {}", e))?;
+        let timestamp = record.timestamp.and_then(|t| match t {
+            serde_json::Value::String(t) => t.parse().ok(),
+            t => t.as_u64(),
+        });
+        if let Some(timestamp) = timestamp {
+            start = Some(start.map_or(timestamp, |s| s.min(timestamp)));
+            end = Some(end.map_or(timestamp, |e| e.max(timestamp)));
+        }
+
+        hosts.push(Host {
+            addresses: vec![import::address(&record.ip)],
+            ports: record
+                .ports
+                .into_iter()
+                .map(|p| parser::Port {
+                    protocol: p.proto,
+                    port: p.port,
+                    service: p.service.and_then(|s| service(&s.name)),
+                    metadata: parser::Metadata::None,
+                    findings: Vec::new(),
+                })
+                .collect(),
+            ..Host::default()
+        });
+    }
+
+    let hosts = import::merge(hosts);
+    let info = ScanInfo {
+        scanner: "masscan".to_string(),
+        start: start.unwrap_or(0),
+        startstr: start.map(history::format_date).unwrap_or_default(),
+        end,
+        endstr: end.map(history::format_date),
+        hosts_up: hosts.len() as u64,
+        hosts_total: hosts.len() as u64,
+        ..ScanInfo::default()
+    };
+
+    Ok((info, hosts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xml_records_are_merged() {
+        let scan = r#"<?xml version="1.0"?>
+<!-- masscan v1.0 scan -->
+<nmaprun scanner="masscan" start="1700000000" version="1.0-BETA"  xmloutputversion="1.03">
+<scaninfo type="syn" protocol="tcp" />
+<host endtime="1700000001"><address addr="10.0.0.1" addrtype="ipv4"/><ports><port protocol="tcp" portid="80"><state state="open" reason="syn-ack" reason_ttl="64"/></port></ports></host>
+<host endtime="1700000001"><address addr="10.0.0.1" addrtype="ipv4"/><ports><port protocol="tcp" portid="22"><state state="open" reason="syn-ack" reason_ttl="64"/></port></ports></host>
+<host endtime="1700000002"><address addr="10.0.0.1" addrtype="ipv4"/><ports><port protocol="tcp" portid="80"><state state="open" reason="syn-ack" reason_ttl="64"/><service name="title" banner="Welcome"></service></port></ports></host>
+<host endtime="1700000002"><address addr="10.0.0.1" addrtype="ipv4"/><ports><port protocol="tcp" portid="80"><state state="open" reason="syn-ack" reason_ttl="64"/><service name="http" banner="HTTP/1.1 200 OK"></service></port></ports></host>
+<host endtime="1700000001"><address addr="10.0.0.2" addrtype="ipv4"/><ports><port protocol="tcp" portid="445"><state state="open" reason="syn-ack" reason_ttl="64"/></port></ports></host>
+<runstats>
+<finished time="1700000010" timestr="2023-11-14 22:13:30" elapsed="10" />
+<hosts up="2" down="0" total="2" />
+</runstats>
+</nmaprun>"#;
+        let (info, hosts) = xml(scan.as_bytes()).unwrap();
+
+        assert_eq!(info.scanner, "masscan");
+        assert_eq!(info.startstr, "2023-11-14 22:13");
+        assert_eq!(
+            import::tests::summary(&hosts),
+            vec!["10.0.0.1: 22/tcp, 80/tcp http", "10.0.0.2: 445/tcp"]
+        );
+    }
+
+    #[test]
+    fn broken_json_is_read_line_by_line() {
+        let scan = r#"[
+{   "ip": "10.0.0.1",   "timestamp": "1700000000", "ports": [ {"port": 80, "proto": "tcp", "status": "open", "reason": "syn-ack", "ttl": 64} ] }
+,
+{   "ip": "10.0.0.1",   "timestamp": "1700000061", "ports": [ {"port": 80, "proto": "tcp", "service": {"name": "http", "banner": "HTTP/1.1"} } ] }
+,
+{   "ip": "fe80::1",   "timestamp": 1700000001, "ports": [ {"port": 22, "proto": "tcp", "status": "open"} ] },
+{finished: 1}
+]"#;
+        let (info, hosts) = json(scan.as_bytes()).unwrap();
+
+        assert_eq!(info.start, 1700000000);
+        assert_eq!(info.endstr.as_deref(), Some("2023-11-14 22:14"));
+        assert_eq!(
+            import::tests::summary(&hosts),
+            vec!["10.0.0.1: 80/tcp http", "fe80::1: 22/tcp"]
+        );
+        assert!(matches!(
+            hosts[1].addresses[0].addr_type,
+            parser::AddrType::IPv6
+        ));
+    }
+
+    #[test]
+    fn invalid_json_records_are_errors() {
+        let error =
+            json("{ \"ip\": \"10.0.0.1\", \"ports\": [ {\"port\": \"http\"} ] }".as_bytes())
+                .unwrap_err();
+        assert!(error.starts_with("invalid masscan record"), "{}", error);
+    }
+}
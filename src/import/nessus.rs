@@ -0,0 +1,224 @@
+//! Nessus `.nessus` reports (`NessusClientData_v2`) list the results of every
+//! plugin per host and port:
+//!
+//! ```xml
+//! <ReportHost name="10.0.0.1">
+//!   <HostProperties><tag name="host-ip">10.0.0.1</tag></HostProperties>
+//!   <ReportItem port="22" svc_name="ssh" protocol="tcp" severity="2" pluginID="90317" pluginName="SSH Weak Algorithms Supported"/>
+//! </ReportHost>
+//! ```
+
+use crate::history;
+use crate::import;
+use crate::parser;
+use crate::parser::Host;
+use crate::parser::ScanInfo;
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::io::BufRead;
+use std::str;
+
+/// Nessus service names that nmap calls differently.
+static SERVICE_NAMES: [(&str, &str); 5] = [
+    ("www", "http"),
+    ("cifs", "microsoft-ds"),
+    ("smb", "microsoft-ds"),
+    ("msrdp", "ms-wbt-server"),
+    ("dns", "domain"),
+];
+
+fn severity(value: &str) -> parser::Severity {
+    match value {
+        "1" => parser::Severity::Low,
+        "2" => parser::Severity::Medium,
+        "3" => parser::Severity::High,
+        "4" => parser::Severity::Critical,
+        _ => parser::Severity::Info,
+    }
+}
+
+/// Adds the port and finding of a `<ReportItem>` to the host. Port 0 holds
+/// findings about the host itself, which are not shown.
+fn report_item(host: &mut Host, item: &BytesStart) -> Result<(), String> {
+    let port: u16 = parser::attribute(item, b"port")?
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0);
+    if port == 0 {
+        return Ok(());
+    }
+
+    let protocol = parser::attribute(item, b"protocol")?.unwrap_or_default();
+    let port = import::port_mut(host, port, &protocol);
+
+    if let Some(name) = parser::attribute(item, b"svc_name")?.filter(|n| !n.ends_with('?')) {
+        let name = SERVICE_NAMES
+            .iter()
+            .find(|(nessus, _)| *nessus == name)
+            .map(|(_, nmap)| nmap.to_string())
+            .unwrap_or(name);
+        port.service.get_or_insert(parser::Service {
+            name: Some(name),
+            product: None,
+            version: None,
+            extrainfo: None,
+        });
+    }
+
+    port.findings.push(parser::Finding {
+        id: parser::attribute(item, b"pluginID")?.unwrap_or_default(),
+        name: parser::attribute(item, b"pluginName")?.unwrap_or_default(),
+        severity: severity(&parser::attribute(item, b"severity")?.unwrap_or_default()),
+    });
+    Ok(())
+}
+
+/// Applies a `<tag>` of the host properties.
+fn host_property(host: &mut Host, info: &mut ScanInfo, name: &str, value: &str) {
+    match name {
+        "host-ip" => host.addresses.insert(0, import::address(value)),
+        "host-fqdn" | "hostname" if !host.hostnames.iter().any(|h| h == value) => {
+            host.hostnames.push(value.to_string());
+        }
+        // alternatives are listed line by line, the first is the most likely
+        "operating-system" => host.os = value.lines().next().map(str::to_string),
+        "mac-address" => {
+            for mac in value.lines() {
+                host.addresses.push(parser::IpAddr {
+                    address: mac.to_string(),
+                    addr_type: parser::AddrType::MAC,
+                });
+            }
+        }
+        "HOST_START_TIMESTAMP" => {
+            if let Ok(start) = value.parse::<u64>() {
+                if info.start == 0 || start < info.start {
+                    info.start = start;
+                }
+            }
+        }
+        "HOST_END_TIMESTAMP" => {
+            if let Ok(end) = value.parse::<u64>() {
+                info.end = Some(info.end.map_or(end, |e| e.max(end)));
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn read<R: BufRead>(input: R) -> Result<(ScanInfo, Vec<Host>), String> {
+    let mut reader = Reader::from_reader(input);
+    reader.trim_text(true);
+
+    let mut info = ScanInfo {
+        scanner: "Nessus".to_string(),
+        ..ScanInfo::default()
+    };
+    let mut hosts = Vec::new();
+    let mut host = Host::default();
+    let mut name = String::new();
+    let mut tag: Option<String> = None;
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event(&mut buf).map_err(|e| e.to_string())? {
+            Event::Start(e) | Event::Empty(e) if e.local_name() == b"Report" => {
+                info.report = parser::attribute(&e, b"name")?;
+            }
+            Event::Start(e) if e.local_name() == b"ReportHost" => {
+                host = Host::default();
+                name = parser::attribute(&e, b"name")?.unwrap_or_default();
+            }
+            Event::Start(e) if e.local_name() == b"tag" => {
+                tag = parser::attribute(&e, b"name")?;
+            }
+            Event::End(e) if e.local_name() == b"tag" => tag = None,
+            Event::Text(e) => {
+                if let Some(name) = tag.take() {
+                    let value = import::text(&e)?;
+                    host_property(&mut host, &mut info, &name, &value);
+                }
+            }
+            Event::Start(e) | Event::Empty(e) if e.local_name() == b"ReportItem" => {
+                report_item(&mut host, &e)?;
+            }
+            Event::End(e) if e.local_name() == b"ReportHost" => {
+                // without a "host-ip" property the host is only known by its name
+                let addressed = host
+                    .addresses
+                    .iter()
+                    .any(|a| !matches!(a.addr_type, parser::AddrType::MAC));
+                if !addressed {
+                    host.addresses.insert(0, import::address(&name));
+                }
+                hosts.push(std::mem::take(&mut host));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    if info.start != 0 {
+        info.startstr = history::format_date(info.start);
+    }
+    info.endstr = info.end.map(history::format_date);
+    info.hosts_up = hosts.len() as u64;
+    info.hosts_total = hosts.len() as u64;
+
+    Ok((info, import::merge(hosts)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hosts_ports_and_findings_are_read() {
+        let report = r#"<?xml version="1.0" ?>
+<NessusClientData_v2>
+<Policy><policyName>Basic</policyName></Policy>
+<Report name="Weekly scan" xmlns:cm="http://www.nessus.org/cm">
+<ReportHost name="10.0.0.1"><HostProperties>
+<tag name="HOST_END_TIMESTAMP">1700000200</tag>
+<tag name="operating-system">Linux Kernel 4.15
+Linux Kernel 5.8</tag>
+<tag name="mac-address">00:11:22:33:44:55</tag>
+<tag name="host-fqdn">gw.example.com</tag>
+<tag name="host-ip">10.0.0.1</tag>
+<tag name="HOST_START_TIMESTAMP">1700000000</tag>
+</HostProperties>
+<ReportItem port="0" svc_name="general" protocol="tcp" severity="0" pluginID="19506" pluginName="Nessus Scan Information" pluginFamily="Settings"><description>x</description></ReportItem>
+<ReportItem port="22" svc_name="ssh" protocol="tcp" severity="2" pluginID="90317" pluginName="SSH Weak Algorithms Supported" pluginFamily="Misc."><risk_factor>Medium</risk_factor></ReportItem>
+<ReportItem port="8443" svc_name="www" protocol="tcp" severity="3" pluginID="1" pluginName="TLS &amp; SSL Weak Ciphers" pluginFamily="Misc."></ReportItem>
+<ReportItem port="8080" svc_name="www?" protocol="tcp" severity="0" pluginID="11219" pluginName="Nessus SYN scanner" pluginFamily="Port scanners"></ReportItem>
+</ReportHost>
+<ReportHost name="files.example.com"><HostProperties><tag name="operating-system">Microsoft Windows Server 2019</tag></HostProperties>
+<ReportItem port="445" svc_name="cifs" protocol="tcp" severity="4" pluginID="97833" pluginName="MS17-010" pluginFamily="Windows"/>
+</ReportHost>
+</Report>
+</NessusClientData_v2>"#;
+        let (info, hosts) = read(report.as_bytes()).unwrap();
+
+        assert_eq!(info.report.as_deref(), Some("Weekly scan"));
+        assert_eq!(info.start, 1700000000);
+        assert_eq!(info.end, Some(1700000200));
+        assert_eq!(
+            import::tests::summary(&hosts),
+            vec![
+                "10.0.0.1 00:11:22:33:44:55 (gw.example.com): 22/tcp ssh, 8080/tcp, 8443/tcp http",
+                "files.example.com: 445/tcp microsoft-ds",
+            ]
+        );
+        assert_eq!(hosts[0].os.as_deref(), Some("Linux Kernel 4.15"));
+
+        let tls = &hosts[0].ports[2].findings[0];
+        assert_eq!(tls.id, "1");
+        assert_eq!(tls.name, "TLS & SSL Weak Ciphers");
+        assert_eq!(tls.severity, parser::Severity::High);
+        assert_eq!(
+            hosts[1].ports[0].findings[0].severity,
+            parser::Severity::Critical
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! nmap's normal output (`-oN`) is meant for humans, so it is read on a best
+//! effort basis: hosts, hostnames, ports with their services, MAC addresses,
+//! the OS and device type, and the traceroute.
+//!
+//! ```text
+//! Nmap scan report for gw.example.com (10.0.0.1)
+//! PORT   STATE SERVICE VERSION
+//! 22/tcp open  ssh     OpenSSH 7.4 (protocol 2.0)
+//! MAC Address: 00:11:22:33:44:55 (Cisco)
+//! ```
+
+use crate::import;
+use crate::parser;
+use crate::parser::Host;
+use crate::parser::ScanInfo;
+use std::io::BufRead;
+
+pub fn read<R: BufRead>(input: R) -> Result<(ScanInfo, Vec<Host>), String> {
+    let mut info = ScanInfo::default();
+    let mut hosts: Vec<Host> = Vec::new();
+    let mut current: Option<Host> = None;
+    let mut traceroute = false;
+
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+
+        if line.starts_with('#') {
+            import::nmap_comment(&line, &mut info);
+        } else if let Some(target) = line.strip_prefix("Nmap scan report for ") {
+            hosts.extend(current.take());
+            // "10.0.0.2 [host down]" is listed with -Pn or -v
+            if !target.ends_with("[host down]") {
+                current = Some(report(target));
+            }
+            traceroute = false;
+        } else if let Some(host) = current.as_mut() {
+            if line.starts_with("TRACEROUTE") {
+                traceroute = true;
+            } else if traceroute {
+                hop(&line, host, &hosts);
+            } else {
+                host_line(&line, host);
+            }
+        }
+    }
+    hosts.extend(current);
+
+    Ok((info, hosts))
+}
+
+/// Creates the host of a "Nmap scan report for" line, its target is either an
+/// address or "hostname (address)".
+fn report(target: &str) -> Host {
+    let mut host = Host::default();
+    match target.split_once(" (") {
+        Some((hostname, address)) => {
+            host.hostnames.push(hostname.to_string());
+            host.addresses
+                .push(import::address(address.trim_end_matches(')')));
+        }
+        None => host.addresses.push(import::address(target)),
+    }
+    host
+}
+
+fn host_line(line: &str, host: &mut Host) {
+    if let Some(mac) = line.strip_prefix("MAC Address: ") {
+        let mac = mac.split_whitespace().next().unwrap_or(mac);
+        host.addresses.push(parser::IpAddr {
+            address: mac.to_string(),
+            addr_type: parser::AddrType::MAC,
+        });
+    } else if let Some(device_type) = line.strip_prefix("Device type: ") {
+        // "general purpose|printer", the first type is the most likely
+        host.os_classes.push(parser::OsClass {
+            device_type: device_type.split('|').next().map(str::to_string),
+            ..parser::OsClass::default()
+        });
+    } else if let Some(service_info) = line.strip_prefix("Service Info: ") {
+        // "OS: Linux; CPE: cpe:/o:linux:linux_kernel"
+        let os = service_info
+            .split("; ")
+            .find_map(|field| field.strip_prefix("OS: "));
+        if let Some(os) = os {
+            host.os = Some(os.to_string());
+        }
+    } else if let Some(port) = port(line) {
+        host.ports.push(port);
+    }
+}
+
+/// Parses a row of the port table, `22/tcp open ssh OpenSSH 7.4`. Ports
+/// that are not open are skipped.
+fn port(line: &str) -> Option<parser::Port> {
+    let mut columns = line.split_whitespace();
+    let (port, protocol) = columns.next()?.split_once('/')?;
+    let port: u16 = port.parse().ok()?;
+    if !columns.next()?.starts_with("open") {
+        return None;
+    }
+
+    let service = columns.next().map(|name| {
+        let (product, extrainfo) =
+            import::version_column(&columns.collect::<Vec<&str>>().join(" "));
+        parser::Service {
+            name: Some(name.to_string()),
+            product,
+            version: None,
+            extrainfo,
+        }
+    });
+
+    Some(parser::Port {
+        protocol: protocol.to_string(),
+        port,
+        service,
+        metadata: parser::Metadata::None,
+        findings: Vec::new(),
+    })
+}
+
+/// Parses a line of the traceroute, `1   0.50 ms 10.0.0.254` or
+/// `1   0.50 ms gw.example.com (10.0.0.254)`. Hops shared with an earlier host
+/// are abbreviated as "-   Hops 1-2 are the same as for 10.0.0.5".
+fn hop(line: &str, host: &mut Host, hosts: &[Host]) {
+    let shared = line.trim_start_matches('-').trim_start();
+    if let Some(same) = shared.strip_prefix("Hops ").or(shared.strip_prefix("Hop ")) {
+        let (range, other) = match same.split_once(" the same as for ") {
+            Some(split) => split,
+            None => return,
+        };
+        let last: usize = range
+            .split_whitespace()
+            .next()
+            .and_then(|r| r.rsplit('-').next())
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0);
+        let other = hosts
+            .iter()
+            .find(|h| h.addresses.iter().any(|a| a.address == other));
+        if let Some(other) = other {
+            host.hops.extend(other.hops.iter().take(last).cloned());
+        }
+        return;
+    }
+
+    // "3   ... 5" marks hops that did not answer
+    let numbered = line
+        .split_whitespace()
+        .next()
+        .is_some_and(|n| n.parse::<u64>().is_ok());
+    if numbered && !line.contains("...") {
+        if let Some(address) = line.split_whitespace().last() {
+            let address = address.trim_start_matches('(').trim_end_matches(')');
+            host.hops.push(address.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SCAN: &str = "# Nmap 7.94 scan initiated Tue Nov 14 22:13:20 2023 as: nmap -oN n.nmap -v -A 10.0.0.0/24
+Nmap scan report for 10.0.0.2 [host down]
+Nmap scan report for gw.example.com (10.0.0.1)
+Host is up (0.0010s latency).
+Not shown: 996 closed tcp ports (reset)
+PORT   STATE    SERVICE VERSION
+22/tcp open     ssh     OpenSSH 7.4 (protocol 2.0)
+| ssh-hostkey: 
+|   2048 aa:bb (RSA)
+23/tcp open     telnet
+25/tcp filtered smtp
+80/tcp open     http    nginx 1.18.0
+|_http-title: Welcome
+MAC Address: 00:11:22:33:44:55 (Cisco)
+Device type: general purpose
+Running: Linux 4.X|5.X
+OS details: Linux 4.15 - 5.8
+Service Info: OS: Linux; CPE: cpe:/o:linux:linux_kernel
+
+TRACEROUTE (using port 80/tcp)
+HOP RTT     ADDRESS
+1   1.00 ms 10.0.0.254
+2   2.00 ms core.example.com (10.0.1.254)
+3   3.00 ms gw.example.com (10.0.0.1)
+
+Nmap scan report for 10.0.0.7
+Host is up (0.0010s latency).
+PORT     STATE SERVICE       VERSION
+445/tcp  open  microsoft-ds  Microsoft Windows Server 2008 R2 - 2012 microsoft-ds
+3389/tcp open  ms-wbt-server Microsoft Terminal Services
+Device type: printer|general purpose
+
+TRACEROUTE (using port 80/tcp)
+HOP RTT     ADDRESS
+-   Hops 1-2 are the same as for 10.0.0.1
+3   ...
+4   4.00 ms 10.0.0.7
+
+OS and Service detection performed. Please report any incorrect results at https://nmap.org/submit/ .
+# Nmap done at Tue Nov 14 22:16:40 2023 -- 256 IP addresses (2 hosts up) scanned in 200.00 seconds
+";
+
+    #[test]
+    fn hosts_and_open_ports_are_read() {
+        let (info, hosts) = read(SCAN.as_bytes()).unwrap();
+
+        assert_eq!(info.version, "7.94");
+        assert_eq!((info.hosts_up, info.hosts_total), (2, 256));
+        assert_eq!(
+            import::tests::summary(&hosts),
+            vec![
+                "10.0.0.1 00:11:22:33:44:55 (gw.example.com): 22/tcp ssh, 23/tcp telnet, 80/tcp http",
+                "10.0.0.7: 445/tcp microsoft-ds, 3389/tcp ms-wbt-server",
+            ]
+        );
+
+        assert_eq!(hosts[0].os.as_deref(), Some("Linux"));
+        let http = hosts[0].ports[2].service.as_ref().unwrap();
+        assert_eq!(http.product.as_deref(), Some("nginx 1.18.0"));
+        assert_eq!(
+            hosts[1].os_classes[0].device_type.as_deref(),
+            Some("printer")
+        );
+    }
+
+    #[test]
+    fn shared_hops_are_copied() {
+        let (_, hosts) = read(SCAN.as_bytes()).unwrap();
+
+        assert_eq!(hosts[0].hops, vec!["10.0.0.254", "10.0.1.254", "10.0.0.1"]);
+        assert_eq!(hosts[1].hops, vec!["10.0.0.254", "10.0.1.254", "10.0.0.7"]);
+    }
+}
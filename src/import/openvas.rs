@@ -0,0 +1,188 @@
+//! OpenVAS / Greenbone xml reports list every result with its host and port:
+//!
+//! ```xml
+//! <result id="...">
+//!   <host>10.0.0.1<hostname>gw.example.com</hostname></host>
+//!   <port>22/tcp</port>
+//!   <nvt oid="1.3.6.1.4.1.25623.1.0.105611"><name>SSH Weak Encryption Algorithms Supported</name></nvt>
+//!   <threat>Medium</threat>
+//! </result>
+//! ```
+//!
+//! Results carry no service names, the host details provide the OS.
+
+use crate::import;
+use crate::parser;
+use crate::parser::Host;
+use crate::parser::ScanInfo;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::io::BufRead;
+use std::str;
+
+fn severity(threat: &str) -> parser::Severity {
+    match threat {
+        "Low" => parser::Severity::Low,
+        "Medium" => parser::Severity::Medium,
+        "High" => parser::Severity::High,
+        "Critical" => parser::Severity::Critical,
+        _ => parser::Severity::Info,
+    }
+}
+
+fn host_mut<'a>(hosts: &'a mut Vec<Host>, ip: &str) -> &'a mut Host {
+    let index = match hosts
+        .iter()
+        .position(|h| h.addresses.iter().any(|a| a.address == ip))
+    {
+        Some(index) => index,
+        None => {
+            hosts.push(Host {
+                addresses: vec![import::address(ip)],
+                ..Host::default()
+            });
+            hosts.len() - 1
+        }
+    };
+    &mut hosts[index]
+}
+
+/// A `<result>` while it is read.
+#[derive(Default)]
+struct Record {
+    ip: String,
+    hostname: String,
+    port: String,
+    oid: String,
+    name: String,
+    threat: String,
+}
+
+impl Record {
+    /// Adds the result to its host. Results on "general/tcp" and the like are
+    /// about the host itself and not shown.
+    fn add(self, hosts: &mut Vec<Host>) {
+        if self.ip.is_empty() {
+            return;
+        }
+
+        let host = host_mut(hosts, &self.ip);
+        if !self.hostname.is_empty() && !host.hostnames.contains(&self.hostname) {
+            host.hostnames.push(self.hostname);
+        }
+
+        let (port, protocol) = self.port.split_once('/').unwrap_or((&self.port, ""));
+        if let Ok(port) = port.parse() {
+            import::port_mut(host, port, protocol)
+                .findings
+                .push(parser::Finding {
+                    id: self.oid,
+                    name: self.name,
+                    severity: severity(&self.threat),
+                });
+        }
+    }
+}
+
+pub fn read<R: BufRead>(input: R) -> Result<(ScanInfo, Vec<Host>), String> {
+    let mut reader = Reader::from_reader(input);
+    reader.trim_text(true);
+
+    let mut info = ScanInfo {
+        scanner: "OpenVAS".to_string(),
+        ..ScanInfo::default()
+    };
+    let mut hosts: Vec<Host> = Vec::new();
+    let mut path: Vec<Vec<u8>> = Vec::new();
+    let mut record = Record::default();
+    // the name of a host detail, e.g. "best_os_txt", and the host it belongs to
+    let mut detail = String::new();
+    let mut detail_host = String::new();
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event(&mut buf).map_err(|e| e.to_string())? {
+            Event::Start(e) => {
+                if e.local_name() == b"result" {
+                    record = Record::default();
+                }
+                if e.local_name() == b"nvt" {
+                    record.oid = parser::attribute(&e, b"oid")?.unwrap_or_default();
+                }
+                path.push(e.local_name().to_vec());
+            }
+            Event::End(e) => {
+                path.pop();
+                if e.local_name() == b"result" && path.ends_with(&[b"results".to_vec()]) {
+                    std::mem::take(&mut record).add(&mut hosts);
+                }
+            }
+            Event::Text(e) => {
+                let text = import::text(&e)?;
+                let names: Vec<&[u8]> = path.iter().rev().take(3).map(|n| &n[..]).collect();
+                match names[..] {
+                    [b"host", b"result", ..] => record.ip = text,
+                    [b"hostname", b"host", b"result"] => record.hostname = text,
+                    [b"port", b"result", ..] => record.port = text,
+                    [b"name", b"nvt", b"result"] => record.name = text,
+                    [b"threat", b"result", ..] => record.threat = text,
+                    [b"scan_start", b"report", ..] => info.startstr = text,
+                    [b"scan_end", b"report", ..] => info.endstr = Some(text),
+                    [b"ip", b"host", b"report"] => detail_host = text,
+                    [b"name", b"detail", b"host"] => detail = text,
+                    [b"value", b"detail", b"host"] if detail == "best_os_txt" => {
+                        host_mut(&mut hosts, &detail_host).os = Some(text);
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    info.hosts_up = hosts.len() as u64;
+    info.hosts_total = hosts.len() as u64;
+
+    Ok((info, hosts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_are_added_to_their_hosts() {
+        let report = r#"<report id="a" format_id="b" extension="xml" content_type="text/xml"><owner><name>admin</name></owner><name>2023-11-14</name>
+<report id="a"><scan_run_status>Done</scan_run_status><scan_start>2023-11-14T22:13:20Z</scan_start>
+<results start="1" max="100">
+<result id="r1"><name>SSH Weak Encryption</name><owner><name>admin</name></owner><host>10.0.0.1<asset asset_id="x"/><hostname>gw.example.com</hostname></host><port>22/tcp</port><nvt oid="1.3.6.1.4.1.25623.1.0.105611"><type>nvt</type><name>SSH Weak Encryption Algorithms Supported</name><family>General</family></nvt><threat>Medium</threat><severity>4.3</severity></result>
+<result id="r2"><name>OS Detection</name><host>10.0.0.1</host><port>general/tcp</port><nvt oid="1.2"><name>OS Detection Consolidation</name></nvt><threat>Log</threat></result>
+<result id="r3"><name>Telnet</name><host>10.0.0.5</host><port>23/tcp</port><nvt oid="CVE-2023&amp;1"><name>Telnet &lt;Unencrypted&gt; Login</name></nvt><threat>High</threat></result>
+</results>
+<host><ip>10.0.0.1</ip><start>x</start><detail><name>best_os_txt</name><value>Linux Kernel</value><source><name>oid</name></source></detail></host>
+<scan_end>2023-11-14T22:20:00Z</scan_end>
+</report></report>"#;
+        let (info, hosts) = read(report.as_bytes()).unwrap();
+
+        assert_eq!(info.scanner, "OpenVAS");
+        assert_eq!(info.startstr, "2023-11-14T22:13:20Z");
+        assert_eq!(info.endstr.as_deref(), Some("2023-11-14T22:20:00Z"));
+        assert_eq!(
+            import::tests::summary(&hosts),
+            vec!["10.0.0.1 (gw.example.com): 22/tcp", "10.0.0.5: 23/tcp"]
+        );
+        assert_eq!(hosts[0].os.as_deref(), Some("Linux Kernel"));
+
+        let ssh = &hosts[0].ports[0].findings[0];
+        assert_eq!(ssh.id, "1.3.6.1.4.1.25623.1.0.105611");
+        assert_eq!(ssh.name, "SSH Weak Encryption Algorithms Supported");
+        assert_eq!(ssh.severity, parser::Severity::Medium);
+
+        let telnet = &hosts[1].ports[0].findings[0];
+        assert_eq!(telnet.id, "CVE-2023&1");
+        assert_eq!(telnet.name, "Telnet <Unencrypted> Login");
+        assert_eq!(telnet.severity, parser::Severity::High);
+    }
+}
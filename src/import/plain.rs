@@ -0,0 +1,197 @@
+//! Plain lists of open ports as written by fast port scanners like rustscan,
+//! naabu and zmap, one port per line:
+//!
+//! ```text
+//! 10.0.0.1:22
+//! Open 10.0.0.1:80
+//! 10.0.0.1 -> [22,80]
+//! 10.0.0.1,443
+//! ```
+//!
+//! or as json lines, `{"ip":"10.0.0.1","port":22,"protocol":"tcp"}`. Neither
+//! carries service data.
+
+use crate::import;
+use crate::parser::Host;
+use crate::parser::ScanInfo;
+use serde::Deserialize;
+use std::io::BufRead;
+
+#[derive(Debug, Deserialize)]
+struct Record {
+    // zmap calls the address of the answering host "saddr"
+    #[serde(alias = "saddr")]
+    ip: Option<String>,
+    host: Option<String>,
+    #[serde(alias = "sport")]
+    port: Option<RecordPort>,
+    protocol: Option<String>,
+}
+
+/// naabu writes the port as a number, older versions as an object.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RecordPort {
+    Number(u16),
+    Object {
+        #[serde(rename = "Port")]
+        port: u16,
+    },
+}
+
+fn host(address: &str, hostname: Option<&str>, ports: Vec<(u16, String)>) -> Host {
+    let mut host = Host {
+        addresses: vec![import::address(address)],
+        ..Host::default()
+    };
+    if let Some(hostname) = hostname.filter(|h| *h != address) {
+        host.hostnames.push(hostname.to_string());
+    }
+    for (port, protocol) in ports {
+        import::port_mut(&mut host, port, &protocol);
+    }
+    host
+}
+
+fn scan_info(hosts: &[Host]) -> ScanInfo {
+    ScanInfo {
+        scanner: "Port list".to_string(),
+        hosts_up: hosts.len() as u64,
+        hosts_total: hosts.len() as u64,
+        ..ScanInfo::default()
+    }
+}
+
+/// Splits an entry into the address and its ports. Returns `None` for lines
+/// that list no port, e.g. headers and banners.
+pub fn entry(line: &str) -> Option<(&str, Vec<u16>)> {
+    let line = line.trim();
+    let line = line.strip_prefix("Open ").unwrap_or(line);
+
+    // rustscan's greppable output, "10.0.0.1 -> [22,80]"
+    if let Some((address, ports)) = line.split_once(" -> ") {
+        let ports = ports.trim_start_matches('[').trim_end_matches(']');
+        let ports: Option<Vec<u16>> = ports.split(',').map(|p| p.trim().parse().ok()).collect();
+        return Some((address, ports?));
+    }
+
+    // zmap's csv output, "10.0.0.1,443"
+    if let Some((address, port)) = line.split_once(',') {
+        return Some((address, vec![port.trim().parse().ok()?]));
+    }
+
+    // "10.0.0.1:22", "[2001:db8::1]:22"
+    let (address, port) = line.rsplit_once(':')?;
+    let address = address.trim_start_matches('[').trim_end_matches(']');
+    if address.is_empty() || address.contains(' ') {
+        return None;
+    }
+    Some((address, vec![port.parse().ok()?]))
+}
+
+pub fn text<R: BufRead>(input: R) -> Result<(ScanInfo, Vec<Host>), String> {
+    let mut hosts = Vec::new();
+
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if let Some((address, ports)) = entry(&line) {
+            let ports = ports.into_iter().map(|p| (p, "tcp".to_string())).collect();
+            hosts.push(host(address, None, ports));
+        }
+    }
+
+    let hosts = import::merge(hosts);
+    Ok((scan_info(&hosts), hosts))
+}
+
+pub fn json<R: BufRead>(input: R) -> Result<(ScanInfo, Vec<Host>), String> {
+    let mut hosts = Vec::new();
+
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let line = line.trim().trim_end_matches(',');
+        if !line.starts_with('{') {
+            continue;
+        }
+
+        let record: Record =
+            serde_json::from_str(line).map_err(|e| format!("invalid port record: {}", e))?;
+        let address = match record.ip.as_deref().or(record.host.as_deref()) {
+            Some(address) => address,
+            None => continue,
+        };
+        let port = match record.port {
+            Some(RecordPort::Number(port)) | Some(RecordPort::Object { port }) => port,
+            None => continue,
+        };
+        let protocol = record.protocol.clone().unwrap_or_else(|| "tcp".to_string());
+        hosts.push(host(
+            address,
+            record.host.as_deref(),
+            vec![(port, protocol)],
+        ));
+    }
+
+    let hosts = import::merge(hosts);
+    Ok((scan_info(&hosts), hosts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries() {
+        assert_eq!(entry("10.0.0.1:22"), Some(("10.0.0.1", vec![22])));
+        assert_eq!(entry("Open 10.0.0.1:80"), Some(("10.0.0.1", vec![80])));
+        assert_eq!(entry("[2001:db8::1]:443"), Some(("2001:db8::1", vec![443])));
+        assert_eq!(
+            entry("10.0.0.1 -> [22,80,8443]"),
+            Some(("10.0.0.1", vec![22, 80, 8443]))
+        );
+        assert_eq!(entry("10.0.0.1,443"), Some(("10.0.0.1", vec![443])));
+        assert_eq!(entry("saddr,sport"), None);
+        assert_eq!(
+            entry("[~] The config file is expected to be at ~/.rustscan.toml"),
+            None
+        );
+        assert_eq!(entry(""), None);
+    }
+
+    #[test]
+    fn port_lists_are_merged_per_host() {
+        let list = "[~] Starting Script(s)
+Open 10.0.0.1:22
+10.0.0.1 -> [22,80]
+saddr,sport
+10.0.0.9,53
+";
+        let (info, hosts) = text(list.as_bytes()).unwrap();
+
+        assert_eq!(info.scanner, "Port list");
+        assert_eq!(info.hosts_up, 2);
+        assert_eq!(
+            import::tests::summary(&hosts),
+            vec!["10.0.0.1: 22/tcp, 80/tcp", "10.0.0.9: 53/tcp"]
+        );
+    }
+
+    #[test]
+    fn json_records_of_naabu_and_zmap() {
+        let records = r#"{"host":"gw.example.com","ip":"10.0.0.1","port":22,"protocol":"tcp","timestamp":"2023-11-14T22:13:20Z"}
+{"ip":"10.0.0.1","port":{"Port":80,"Protocol":0}}
+{"saddr":"10.0.0.3","sport":53,"protocol":"udp","success":1}
+{"ip":"10.0.0.4"}
+"#;
+        let (_, hosts) = json(records.as_bytes()).unwrap();
+
+        assert_eq!(
+            import::tests::summary(&hosts),
+            vec![
+                "10.0.0.1 (gw.example.com): 22/tcp, 80/tcp",
+                "10.0.0.3: 53/udp"
+            ]
+        );
+        assert!(json("{\"ip\":\"10.0.0.1\",\"port\":\"ssh\"}".as_bytes()).is_err());
+    }
+}
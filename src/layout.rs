@@ -0,0 +1,77 @@
+use crate::font;
+use crate::renderer;
+use crate::server;
+
+/// Positions of a set of boxes packed into a rectangular area.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    /// Top left corner of every box relative to the area, in input order.
+    pub positions: Vec<[u64; 2]>,
+    pub width: u64,
+    pub height: u64,
+}
+
+/// Width of a column fitting its widest value, at least `min_width` and at
+/// most `max_width` if given.
+pub fn column_width(values: &[String], min_width: u64, max_width: Option<u64>) -> u64 {
+    let width = values
+        .iter()
+        .map(|v| font::cell_width(v, renderer::FONT_SIZE))
+        .fold(min_width, u64::max);
+
+    match max_width {
+        Some(max_width) => width.min(max_width),
+        None => width,
+    }
+}
+
+/// Size of an expanded server box, a header and one row per item, as wide as
+/// its widest row. The first item shares its row with the device icon.
+pub fn server_size(server: &server::Server, max_width: Option<u64>) -> [u64; 2] {
+    let mut values: Vec<String> = server.items.iter().map(|i| i.value()).collect();
+    let first_width = values
+        .first()
+        .map(|v| column_width(std::slice::from_ref(v), 0, max_width) + renderer::ICON_WIDTH)
+        .unwrap_or(0);
+    values.push(server.header());
+    [
+        column_width(&values, renderer::SERVER_ENTRY_WIDTH, max_width).max(first_width),
+        renderer::SERVER_ENTRY_HEIGHT * (server.items.len() + 1) as u64,
+    ]
+}
+
+/// Packs boxes into rows ("shelves") no wider than `max_width`, using next-fit
+/// decreasing height: the tallest boxes are placed first, so each row only
+/// holds boxes of similar height and little space is wasted below the shorter
+/// ones. `padding` is kept between boxes and around the whole area.
+pub fn shelves(sizes: &[[u64; 2]], max_width: u64, padding: u64) -> Layout {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|a, b| sizes[*b][1].cmp(&sizes[*a][1]));
+
+    let mut positions = vec![[0, 0]; sizes.len()];
+    let mut width = 0;
+    let mut x = padding;
+    let mut y = padding;
+    let mut shelf_height = 0;
+
+    for index in order {
+        let [box_width, box_height] = sizes[index];
+
+        if x > padding && x + box_width + padding > max_width {
+            x = padding;
+            y += shelf_height + padding;
+            shelf_height = 0;
+        }
+
+        positions[index] = [x, y];
+        x += box_width + padding;
+        width = width.max(x);
+        shelf_height = shelf_height.max(box_height);
+    }
+
+    Layout {
+        positions,
+        width: width.max(2 * padding),
+        height: y + shelf_height + padding,
+    }
+}
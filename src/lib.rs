@@ -0,0 +1,183 @@
+//! Renders the xml-output of `nmap` as a [drawio](https://app.diagrams.net/) diagram.
+//!
+//! The pipeline is split into modules that can be used on their own:
+//! `parser` reads the scan into `parser::Host`s, `server` and `detail` turn
+//! them into the server boxes and service tables of the diagram, `layout`
+//! packs the boxes and `renderer` draws them on a `renderer::Drawio` canvas.
+//! `render` runs the whole pipeline.
+
+#![allow(clippy::upper_case_acronyms)]
+
+use std::collections::BTreeMap;
+
+pub mod category;
+pub mod detail;
+pub mod diff;
+pub mod filter;
+pub mod font;
+pub mod history;
+pub mod icon;
+pub mod import;
+pub mod layout;
+pub mod parser;
+pub mod renderer;
+pub mod scope;
+pub mod selection;
+pub mod server;
+pub mod theme;
+pub mod update;
+
+static TABLE_PADDING: u64 = 30;
+
+/// Settings of the rendering pipeline.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Maximum width of server and table columns in pixels, longer values
+    /// are shortened with an ellipsis.
+    pub max_width: Option<u64>,
+    /// Draw edges from the service tables to the matching server ports.
+    pub edges: bool,
+    pub theme: theme::Theme,
+    /// Collapse server boxes with more ports than this to their header.
+    pub collapse_ports: Option<usize>,
+    /// Colour the server ports by category and add a legend of the categories.
+    pub categories: Option<category::Categories>,
+    /// Ports drawn in server boxes and service tables.
+    pub ports: selection::PortSelection,
+    /// Hosts outside the scope are drawn greyed out in a network of their own
+    /// and left out of the service tables.
+    pub scope: Option<scope::Scope>,
+    /// What the service tables are grouped by.
+    pub grouping: detail::Grouping,
+    /// How finely ports are grouped into service tables.
+    pub granularity: detail::Granularity,
+}
+
+/// Sorts the hosts into networks by the number of hops needed to reach them.
+pub fn topology(hosts: &[parser::Host]) -> BTreeMap<u64, Vec<&parser::Host>> {
+    let mut topology: BTreeMap<u64, Vec<&parser::Host>> = BTreeMap::new();
+    for host in hosts {
+        topology
+            .entry(host.hops.len() as u64)
+            .or_default()
+            .push(host);
+    }
+    topology
+}
+
+/// Renders the legend, one network per hop distance and the service tables.
+pub fn render(
+    info: &parser::ScanInfo,
+    hosts: &[parser::Host],
+    options: &Options,
+) -> renderer::Drawio {
+    let mut canvas = renderer::Drawio::new();
+    canvas.set_theme(options.theme.clone());
+    canvas.set_max_width(options.max_width);
+    canvas.set_edges(options.edges);
+    canvas.set_collapse_ports(options.collapse_ports);
+    canvas.set_categories(options.categories.clone());
+    let mut tables =
+        detail::Tables::new(options.grouping, options.granularity, options.ports.clone());
+
+    // the legend and networks are stacked, `area` covers all of them
+    let legend = canvas.legend(info, &[10, 10], &"1".to_string(), "legend".to_string());
+    let mut area = legend;
+
+    let (hosts, out_of_scope): (Vec<parser::Host>, Vec<parser::Host>) = match &options.scope {
+        Some(scope) => hosts.iter().cloned().partition(|h| scope.contains(h)),
+        None => (hosts.to_vec(), Vec::new()),
+    };
+    let out_of_scope: Vec<server::Server> = out_of_scope
+        .into_iter()
+        .map(|host| {
+            let mut server = server::Server::into_items(host, &options.ports);
+            server.out_of_scope = true;
+            server
+        })
+        .collect();
+
+    let networks: Vec<(u64, Vec<server::Server>)> = topology(&hosts)
+        .into_iter()
+        .map(|(distance, servers)| {
+            let itemized_servers = servers
+                .iter()
+                .map(|s| server::Server::into_items(s.to_owned().to_owned(), &options.ports))
+                .collect();
+            (distance, itemized_servers)
+        })
+        .collect();
+
+    if let Some(categories) = &options.categories {
+        let servers: Vec<&server::Server> = networks
+            .iter()
+            .flat_map(|(_, servers)| servers.iter())
+            .chain(out_of_scope.iter())
+            .collect();
+        let counts = categories.count(&servers);
+        if !counts.is_empty() {
+            let bounds = canvas.category_legend(
+                &counts,
+                &[10, area[1] + area[3] + 10],
+                &"1".to_string(),
+                "categories".to_string(),
+            );
+            area[3] = bounds[1] + bounds[3] - area[1];
+        }
+    }
+
+    for (distance, itemized_servers) in networks {
+        let bounds = canvas.network(
+            itemized_servers,
+            &[10, area[1] + area[3] + 10],
+            &"1".to_string(),
+            format!("network-{}", distance),
+        );
+        area[2] = area[2].max(bounds[0] + bounds[2] - area[0]);
+        area[3] = bounds[1] + bounds[3] - area[1];
+    }
+
+    if !out_of_scope.is_empty() {
+        let bounds = canvas.out_of_scope(
+            out_of_scope,
+            &[10, area[1] + area[3] + 10],
+            &"1".to_string(),
+            "network-out-of-scope".to_string(),
+        );
+        area[2] = area[2].max(bounds[0] + bounds[2] - area[0]);
+        area[3] = bounds[1] + bounds[3] - area[1];
+    }
+
+    for host in &hosts {
+        tables.add_host(host);
+    }
+    tables.sort();
+
+    // service tables are wrapped into a region as wide as the networks, right of them
+    let sizes: Vec<[u64; 2]> = tables
+        .services
+        .iter()
+        .map(|s| canvas.service_size(s))
+        .collect();
+    let tables_layout = layout::shelves(&sizes, area[2], TABLE_PADDING);
+    let origin = [area[0] + area[2], area[1]];
+
+    for (index, service) in tables.services.into_iter().enumerate() {
+        let position = tables_layout.positions[index];
+        let location = [origin[0] + position[0], origin[1] + position[1]];
+
+        let id = renderer::Drawio::cell_id("table", &service.key());
+        canvas.service(service, &location, &"1".to_string(), id);
+    }
+
+    canvas
+}
+
+/// Renders all hosts that changed between two scans.
+pub fn render_diff(changes: &diff::ScanDiff, options: &Options) -> renderer::Drawio {
+    let mut canvas = renderer::Drawio::new();
+    canvas.set_theme(options.theme.clone());
+    canvas.set_max_width(options.max_width);
+    canvas.changes(changes, &[10, 10], &"1".to_string(), "diff".to_string());
+    canvas
+}
@@ -1,64 +1,268 @@
-use std::collections::BTreeMap;
+use networkplan_renderer::category::Categories;
+use networkplan_renderer::detail::Granularity;
+use networkplan_renderer::detail::Grouping;
+use networkplan_renderer::diff;
+use networkplan_renderer::filter;
+use networkplan_renderer::filter::Filter;
+use networkplan_renderer::history;
+use networkplan_renderer::import;
+use networkplan_renderer::parser;
+use networkplan_renderer::scope::Scope;
+use networkplan_renderer::selection::PortSelection;
+use networkplan_renderer::theme::Theme;
+use networkplan_renderer::update;
+use networkplan_renderer::Options;
+use std::env;
+use std::fs;
+use std::process;
 
-mod detail;
-mod parser;
-mod renderer;
-mod server;
+fn main() {
+    let args: Vec<String> = env::args().collect();
 
-/* Line:
-<mxCell id="QOGmrZeb03ETIo_Szw0Q-2" style="edgeStyle=orthogonalEdgeStyle;shape=link;rounded=0;orthogonalLoop=1;jettySize=auto;html=1;exitX=0.5;exitY=1;exitDx=0;exitDy=0;entryX=0.5;entryY=1;entryDx=0;entryDy=0;" edge="1" parent="1" source="network-network-1-17-3" target="network-network-1-18-3">
-    <mxGeometry relative="1" as="geometry"/>
-</mxCell>
-*/
+    match args.get(1).map(|a| a.as_str()) {
+        Some("diff") => compare(Args::parse(&args[2..])),
+        Some("ingest") => ingest(Args::parse(&args[2..])),
+        Some("asof") => as_of(Args::parse(&args[2..])),
+        Some("timeline") => timeline(Args::parse(&args[2..])),
+        Some("update") => update_diagram(Args::parse(&args[2..])),
+        Some(_) => draw(Args::parse(&args[1..])),
+        None => draw(Args::parse(&["./testfiles/output1.xml".to_string()])),
+    }
+}
 
-fn main() {
-    let hosts = parser::Parser::parse("./testfiles/output1.xml".to_string());
+fn usage() -> ! {
+    eprintln!("Usage: networkplan-renderer <scan>... [options]");
+    eprintln!("       networkplan-renderer diff <old.xml> <new.xml> [--json] [options]");
+    eprintln!("       networkplan-renderer ingest <scan.xml>... [--store <dir>]");
+    eprintln!("       networkplan-renderer asof <YYYY-MM-DD|timestamp> [--store <dir>] [options]");
+    eprintln!("       networkplan-renderer timeline [address] [--store <dir>]");
+    eprintln!("       networkplan-renderer update <diagram.drawio> <scan.xml> [options]");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --max-width <px>    shorten values wider than <px> with an ellipsis");
+    eprintln!("  --collapse <ports>  collapse servers with more than <ports> ports");
+    eprintln!("  --edges             connect service table rows to the server ports");
+    eprintln!("  --theme <theme>     light (default), dark, print or a theme file");
+    eprintln!("  --categories <map>  colour ports by category, default or a category file");
+    eprintln!(
+        "  --tables <by>       group tables by service (default), name, port, product or host"
+    );
+    eprintln!("  --include <ports>   only draw these ports, e.g. 22,80,8000-8100");
+    eprintln!("  --exclude <ports>   leave these ports out, e.g. 135,139");
+    eprintln!("  --interesting       only draw commonly interesting ports");
+    eprintln!("  --scope <file>      grey out hosts outside the scope file");
+    eprintln!("  --filter <e>        only draw the hosts matching <e>");
+    eprintln!("  --port-filter <e>   only draw the ports matching <e>");
+    eprintln!("  --granularity <g>   one service table per name, product or version (default)");
+    process::exit(1);
+}
+
+struct Args {
+    positional: Vec<String>,
+    store: String,
+    json: bool,
+    filter: Option<Filter>,
+    port_filter: Option<Filter>,
+    options: Options,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Args {
+        let mut parsed = Args {
+            positional: Vec::new(),
+            store: history::DEFAULT_STORE.to_string(),
+            json: false,
+            filter: None,
+            port_filter: None,
+            options: Options::default(),
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--store" => parsed.store = Args::value(args.next()),
+                "--json" => parsed.json = true,
+                "--include" => parsed
+                    .options
+                    .ports
+                    .include
+                    .extend(Args::ranges(args.next())),
+                "--exclude" => parsed
+                    .options
+                    .ports
+                    .exclude
+                    .extend(Args::ranges(args.next())),
+                "--interesting" => parsed
+                    .options
+                    .ports
+                    .include
+                    .extend(PortSelection::interesting().include),
+                "--scope" => {
+                    parsed.options.scope = Some(
+                        Scope::load(&Args::value(args.next())).expect("Could not load scope file"),
+                    )
+                }
+                "--filter" => parsed.filter = Some(Args::filter(args.next())),
+                "--port-filter" => parsed.port_filter = Some(Args::filter(args.next())),
+                "--edges" => parsed.options.edges = true,
+                "--categories" => {
+                    parsed.options.categories = Some(
+                        Categories::load(&Args::value(args.next()))
+                            .expect("Could not load category file"),
+                    )
+                }
+                "--theme" => {
+                    parsed.options.theme =
+                        Theme::load(&Args::value(args.next())).expect("Could not load theme")
+                }
+                "--collapse" => {
+                    parsed.options.collapse_ports =
+                        Some(Args::value(args.next()).parse().unwrap_or_else(|_| usage()))
+                }
+                "--tables" => {
+                    parsed.options.grouping =
+                        Grouping::parse(&Args::value(args.next())).unwrap_or_else(|| usage())
+                }
+                "--granularity" => {
+                    parsed.options.granularity =
+                        Granularity::parse(&Args::value(args.next())).unwrap_or_else(|| usage())
+                }
+                "--max-width" => {
+                    parsed.options.max_width =
+                        Some(Args::value(args.next()).parse().unwrap_or_else(|_| usage()))
+                }
+                flag if flag.starts_with("--") => usage(),
+                _ => parsed.positional.push(arg.to_string()),
+            }
+        }
+
+        parsed
+    }
+
+    fn value(arg: Option<&String>) -> String {
+        arg.unwrap_or_else(|| usage()).to_string()
+    }
+
+    fn ranges(arg: Option<&String>) -> Vec<(u16, u16)> {
+        PortSelection::ranges(&Args::value(arg)).unwrap_or_else(|| usage())
+    }
+
+    fn filter(arg: Option<&String>) -> Filter {
+        Filter::parse(&Args::value(arg)).unwrap_or_else(|e| {
+            eprintln!("Invalid filter: {}", e);
+            process::exit(1);
+        })
+    }
+
+    /// Applies `--filter` and `--port-filter` to the hosts of a scan.
+    fn apply(&self, hosts: Vec<parser::Host>) -> Vec<parser::Host> {
+        filter::apply(hosts, self.filter.as_ref(), self.port_filter.as_ref())
+    }
+}
 
-    // sort server into network categories
+fn draw(args: Args) {
+    if args.positional.is_empty() {
+        usage();
+    }
 
-    let mut topology: BTreeMap<u64, Vec<&parser::Host>> = BTreeMap::new();
-    for host in &hosts {
-        topology
-            .entry(host.hops.len() as u64)
-            .or_insert(Vec::new())
-            .push(host);
+    render(
+        import::read_all(&args.positional).expect("Could not read scan"),
+        &args,
+    );
+}
+
+fn ingest(args: Args) {
+    if args.positional.is_empty() {
+        usage();
     }
 
-    let mut canvas = renderer::Drawio::new();
-    let mut tables = detail::Tables::new();
+    let history = history::History::open(args.store).expect("Could not open history");
+    for file in args.positional {
+        let start = history.ingest(file.clone()).unwrap_or_else(|e| {
+            eprintln!("Could not ingest {}: {}", file, e);
+            process::exit(1);
+        });
+        println!("{}: {}", history::format_date(start), file);
+    }
+}
 
-    let mut id: u64 = 1;
-    let mut used_height = 10;
+fn as_of(args: Args) {
+    if args.positional.len() != 1 {
+        usage();
+    }
 
-    for (distance, servers) in topology {
-        let itemized_servers = servers
-            .iter()
-            .map(|s| server::Server::into_items(s.to_owned().to_owned()))
-            .collect();
+    let history = history::History::open(args.store.clone()).expect("Could not open history");
+    let time = history::parse_date(&args.positional[0]).unwrap_or_else(|| usage());
+    match history.as_of(time).expect("Could not read history") {
+        Some(snapshot) => {
+            println!(
+                "Rendering scan from {}",
+                history::format_date(snapshot.start)
+            );
+            render(snapshot.parse().expect("Could not read scan"), &args);
+        }
+        None => {
+            eprintln!("No scan in the history before {}", args.positional[0]);
+            process::exit(1);
+        }
+    }
+}
 
-        used_height = canvas.network(
-            itemized_servers,
-            &[10, used_height],
-            &"1".to_string(),
-            format!("network-{}", distance),
-        ) + 10;
-        id += 1;
+fn timeline(args: Args) {
+    if args.positional.len() > 1 {
+        usage();
     }
 
-    for host in &hosts {
-        tables.add_host(&host);
+    let history = history::History::open(args.store).expect("Could not open history");
+    println!(
+        "{}",
+        history
+            .timeline(args.positional.first().map(|a| a.as_str()))
+            .expect("Could not read history")
+    );
+}
+
+fn update_diagram(args: Args) {
+    if args.positional.len() != 2 {
+        usage();
     }
 
-    //println!("{:#?}", tables.services);
-    for service in tables.services {
-        let location = [
-            1000 + id * (renderer::IP_ENTRY_WIDTH + renderer::PORT_ENTRY_WIDTH + 30),
-            renderer::SERVER_ENTRY_HEIGHT,
-        ];
+    let diagram = &args.positional[0];
+    let existing = fs::read_to_string(diagram).expect("Could not read diagram");
+    let (info, hosts) = import::read(args.positional[1].to_string()).expect("Could not read scan");
+    let hosts = args.apply(hosts);
+    let canvas = networkplan_renderer::render(&info, &hosts, &args.options);
+
+    let updated = update::update(&existing, &canvas.xml()).expect("Could not update diagram");
+    fs::write(diagram, updated).expect("Could not write diagram");
+}
+
+fn compare(args: Args) {
+    if args.positional.len() != 2 {
+        usage();
+    }
 
-        canvas.service(service, &location, &"1".to_string(), format!("table{}", id));
-        id += 1;
+    let (_, old) = import::read(args.positional[0].to_string()).expect("Could not read scan");
+    let (_, new) = import::read(args.positional[1].to_string()).expect("Could not read scan");
+    let mut changes = diff::ScanDiff::compare(&args.apply(old), &args.apply(new));
+    if let Some(scope) = &args.options.scope {
+        changes.mark_scope(scope);
     }
 
-    canvas.export("./export.drawio".to_string());
+    if args.json {
+        println!("{}", changes.json());
+    } else {
+        println!("{}", changes.changelog());
+    }
+
+    networkplan_renderer::render_diff(&changes, &args.options)
+        .export("./export.drawio".to_string())
+        .expect("Could not write diagram");
+}
+
+fn render((info, hosts): (parser::ScanInfo, Vec<parser::Host>), args: &Args) {
+    let hosts = args.apply(hosts);
+    networkplan_renderer::render(&info, &hosts, &args.options)
+        .export("./export.drawio".to_string())
+        .expect("Could not write diagram");
 }
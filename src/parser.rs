@@ -1,13 +1,18 @@
+use flate2::read::MultiGzDecoder;
+use quick_xml::events::BytesStart;
 use quick_xml::events::Event;
 use quick_xml::Reader;
 use std::fs::File;
+use std::io;
+use std::io::BufRead;
 use std::io::BufReader;
+use std::io::Read;
 
 use std::str;
 
 #[derive(Debug, Clone)]
 pub enum Metadata {
-    SSH(String),
+    SSH(Vec<String>),
     None,
 }
 
@@ -20,21 +25,49 @@ enum ParserState {
     Ports,
     Port,
     Hops,
+    OS,
     Done,
 
     SSH,
     FINGERPRINT,
+    FINGERPRINTVALUE,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Host {
     pub addresses: Vec<IpAddr>,
     pub hostnames: Vec<String>,
     pub ports: Vec<Port>,
     pub os: Option<String>,
+    /// OS detection guesses, most accurate first.
+    pub os_classes: Vec<OsClass>,
     pub hops: Vec<String>,
 }
 
+impl Host {
+    /// Identifies the host across records and scans: its first ip address, or
+    /// its MAC address or hostname if it has none.
+    pub(crate) fn key(&self) -> Option<String> {
+        self.addresses
+            .iter()
+            .find(|a| !matches!(a.addr_type, AddrType::MAC))
+            .or_else(|| self.addresses.first())
+            .map(|a| a.address.clone())
+            .or_else(|| self.hostnames.first().cloned())
+    }
+}
+
+/// One `<osclass>` guess of nmap's OS detection.
+#[derive(Debug, Clone, Default)]
+pub struct OsClass {
+    /// Device type, e.g. "general purpose", "printer" or "switch".
+    pub device_type: Option<String>,
+    pub vendor: Option<String>,
+    pub family: Option<String>,
+    pub generation: Option<String>,
+    pub accuracy: u8,
+}
+
 #[derive(Debug, Clone)]
 pub struct DuplicateKeys {
     pub addresses: Vec<IpAddr>,
@@ -49,12 +82,12 @@ pub enum AddrType {
 }
 
 impl AddrType {
-    fn parse(name: &[u8]) -> AddrType {
+    fn parse(name: &str) -> Result<AddrType, String> {
         match name {
-            b"mac" => AddrType::MAC,
-            b"ipv4" => AddrType::IPv4,
-            b"ipv6" => AddrType::IPv6,
-            _ => unreachable!("There should be no other AddrType!"),
+            "mac" => Ok(AddrType::MAC),
+            "ipv4" => Ok(AddrType::IPv4),
+            "ipv6" => Ok(AddrType::IPv6),
+            _ => Err(format!("unknown address type \"{}\"", name)),
         }
     }
 }
@@ -71,9 +104,41 @@ pub struct Port {
     pub port: u16,
     pub service: Option<Service>,
     pub metadata: Metadata,
+    /// Findings of vulnerability scanners on this port.
+    pub findings: Vec<Finding>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Info => "Info",
+            Severity::Low => "Low",
+            Severity::Medium => "Medium",
+            Severity::High => "High",
+            Severity::Critical => "Critical",
+        }
+    }
+}
+
+/// A result of a vulnerability scanner, e.g. a Nessus plugin or an OpenVAS NVT.
 #[derive(Debug, Clone)]
+pub struct Finding {
+    /// Plugin id or NVT oid.
+    pub id: String,
+    pub name: String,
+    pub severity: Severity,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Service {
     pub name: Option<String>,
     pub product: Option<String>,
@@ -81,9 +146,78 @@ pub struct Service {
     pub extrainfo: Option<String>,
 }
 
+/// One `<scaninfo>` entry, e.g. a SYN scan of 1000 tcp ports.
+#[derive(Debug, Clone, Default)]
+pub struct ScanType {
+    pub scan_type: String,
+    pub protocol: String,
+    pub numservices: u64,
+    pub services: String,
+}
+
+/// Metadata of the nmap run taken from `<nmaprun>`, `<scaninfo>` and `<runstats>`.
+#[derive(Debug, Clone, Default)]
+pub struct ScanInfo {
+    /// Name of the scanner, e.g. "nmap" or "masscan".
+    pub scanner: String,
+    pub args: String,
+    /// Name of the report of a vulnerability scanner.
+    pub report: Option<String>,
+    pub version: String,
+    pub start: u64,
+    pub startstr: String,
+    pub end: Option<u64>,
+    pub endstr: Option<String>,
+    pub summary: Option<String>,
+    pub scans: Vec<ScanType>,
+    pub hosts_up: u64,
+    pub hosts_down: u64,
+    pub hosts_total: u64,
+}
+
+/// Attributes of an element with their entities like `&amp;` resolved.
+pub(crate) fn attributes(e: &BytesStart) -> Result<Vec<(Vec<u8>, String)>, String> {
+    e.attributes()
+        .map(|a| {
+            let a = a.map_err(|e| e.to_string())?;
+            let value = a.unescaped_value().map_err(|e| e.to_string())?;
+            let value = str::from_utf8(&value).map_err(|e| e.to_string())?;
+            Ok((a.key.to_vec(), value.to_string()))
+        })
+        .collect()
+}
+
+pub(crate) fn attribute(e: &BytesStart, key: &[u8]) -> Result<Option<String>, String> {
+    Ok(attributes(e)?
+        .into_iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value))
+}
+
+/// An attribute nmap always writes.
+fn required(e: &BytesStart, key: &[u8]) -> Result<String, String> {
+    attribute(e, key)?.ok_or_else(|| {
+        format!(
+            "<{}> without {}",
+            String::from_utf8_lossy(e.local_name()),
+            String::from_utf8_lossy(key)
+        )
+    })
+}
+
+fn number<T: str::FromStr>(key: &[u8], value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| {
+        format!(
+            "{} \"{}\" is not a number",
+            String::from_utf8_lossy(key),
+            value
+        )
+    })
+}
+
 pub struct Parser {
     state: ParserState,
-    hosts: Vec<Host>,
+    info: ScanInfo,
     current_host: Host,
     current_port: Option<Port>,
 }
@@ -92,87 +226,103 @@ impl Parser {
     fn new() -> Parser {
         Parser {
             state: ParserState::Ignore,
-            hosts: Vec::new(),
-            current_host: Host {
-                addresses: Vec::new(),
-                hostnames: Vec::new(),
-                ports: Vec::new(),
-                os: None,
-                hops: Vec::new(),
-            },
+            info: ScanInfo::default(),
+            current_host: Host::default(),
             current_port: None,
         }
     }
 
-    fn process(&mut self, ev: Event) {
+    /// Advances the state machine by one event and returns the host once its
+    /// closing tag has been seen.
+    fn process(&mut self, ev: Event) -> Result<Option<Host>, String> {
         // println!("Current State: {:?}, Event: {:?}", self.state, ev);
+        let mut finished = None;
 
         self.state = match self.state {
             ParserState::Ignore => match ev {
-                Event::Start(e) if e.local_name() == b"nmaprun" => ParserState::WaitingForHost,
+                Event::Start(e) if e.local_name() == b"nmaprun" => {
+                    for (key, value) in attributes(&e)? {
+                        match key.as_slice() {
+                            b"scanner" => self.info.scanner = value,
+                            b"args" => self.info.args = value,
+                            b"version" => self.info.version = value,
+                            b"start" => self.info.start = number(b"start", &value)?,
+                            b"startstr" => self.info.startstr = value,
+                            _ => {}
+                        }
+                    }
+                    ParserState::WaitingForHost
+                }
                 _ => ParserState::Ignore,
             },
             ParserState::WaitingForHost => match ev {
                 Event::Start(e) if e.local_name() == b"host" => {
-                    self.current_host = Host {
-                        addresses: Vec::new(),
-                        hostnames: Vec::new(),
-                        ports: Vec::new(),
-                        os: None,
-                        hops: Vec::new(),
-                    };
+                    self.current_host = Host::default();
                     ParserState::Host
                 }
+                Event::Empty(e) if e.local_name() == b"scaninfo" => {
+                    let mut scan = ScanType::default();
+                    for (key, value) in attributes(&e)? {
+                        match key.as_slice() {
+                            b"type" => scan.scan_type = value,
+                            b"protocol" => scan.protocol = value,
+                            b"numservices" => scan.numservices = number(b"numservices", &value)?,
+                            b"services" => scan.services = value,
+                            _ => {}
+                        }
+                    }
+                    self.info.scans.push(scan);
+                    ParserState::WaitingForHost
+                }
+                Event::Empty(e) if e.local_name() == b"finished" => {
+                    for (key, value) in attributes(&e)? {
+                        match key.as_slice() {
+                            b"time" => self.info.end = Some(number(b"time", &value)?),
+                            b"timestr" => self.info.endstr = Some(value),
+                            b"summary" => self.info.summary = Some(value),
+                            _ => {}
+                        }
+                    }
+                    ParserState::WaitingForHost
+                }
+                Event::Empty(e) if e.local_name() == b"hosts" => {
+                    for (key, value) in attributes(&e)? {
+                        let value = number(&key, &value)?;
+                        match key.as_slice() {
+                            b"up" => self.info.hosts_up = value,
+                            b"down" => self.info.hosts_down = value,
+                            b"total" => self.info.hosts_total = value,
+                            _ => {}
+                        }
+                    }
+                    ParserState::WaitingForHost
+                }
                 Event::End(e) if e.local_name() == b"nmaprun" => ParserState::Done,
                 _ => ParserState::WaitingForHost,
             },
             ParserState::Host => match ev {
                 Event::Empty(e) if e.local_name() == b"address" => {
-                    // do filter magic
-                    let addr = e
-                        .attributes()
-                        .find(|a| a.as_ref().unwrap().key == b"addr")
-                        .unwrap()
-                        .unwrap()
-                        .value;
-
-                    let addr_type = e
-                        .attributes()
-                        .find(|a| a.as_ref().unwrap().key == b"addrtype")
-                        .unwrap()
-                        .unwrap()
-                        .value;
-
-                    let addr_type = AddrType::parse(addr_type.as_ref());
-
-                    self.current_host.addresses.push(IpAddr {
-                        address: str::from_utf8(&addr).unwrap().to_string(),
-                        addr_type,
-                    });
+                    let address = required(&e, b"addr")?;
+                    let addr_type = AddrType::parse(&required(&e, b"addrtype")?)?;
+
+                    self.current_host
+                        .addresses
+                        .push(IpAddr { address, addr_type });
                     ParserState::Host
                 }
                 Event::Start(e) if e.local_name() == b"hostnames" => ParserState::Hostnames,
                 Event::Start(e) if e.local_name() == b"ports" => ParserState::Ports,
                 Event::Start(e) if e.local_name() == b"trace" => ParserState::Hops,
+                Event::Start(e) if e.local_name() == b"os" => ParserState::OS,
                 Event::End(e) if e.local_name() == b"host" => {
-                    self.hosts.push(self.current_host.clone());
+                    finished = Some(std::mem::take(&mut self.current_host));
                     ParserState::WaitingForHost
                 }
                 _ => ParserState::Host,
             },
             ParserState::Hostnames => match ev {
                 Event::Empty(e) if e.local_name() == b"hostname" => {
-                    // do filter magic
-                    let hostname = e
-                        .attributes()
-                        .find(|a| a.as_ref().unwrap().key == b"name")
-                        .unwrap()
-                        .unwrap()
-                        .value;
-
-                    self.current_host
-                        .hostnames
-                        .push(str::from_utf8(&hostname).unwrap().to_string());
+                    self.current_host.hostnames.push(required(&e, b"name")?);
                     ParserState::Hostnames
                 }
                 Event::End(e) if e.local_name() == b"hostnames" => ParserState::Host,
@@ -180,31 +330,12 @@ impl Parser {
             },
             ParserState::Ports => match ev {
                 Event::Start(e) if e.local_name() == b"port" => {
-                    // do filter magic
-                    let protocol = e
-                        .attributes()
-                        .find(|a| a.as_ref().unwrap().key == b"protocol")
-                        .unwrap()
-                        .unwrap()
-                        .value;
-
-                    let port = e
-                        .attributes()
-                        .find(|a| a.as_ref().unwrap().key == b"portid")
-                        .unwrap()
-                        .unwrap()
-                        .value;
-                    let service = None;
-
                     let port = Port {
-                        protocol: str::from_utf8(&protocol).unwrap().to_string(),
-                        port: str::from_utf8(&port)
-                            .unwrap()
-                            .to_string()
-                            .parse::<u16>()
-                            .unwrap(),
-                        service,
+                        protocol: required(&e, b"protocol")?,
+                        port: number(b"portid", &required(&e, b"portid")?)?,
+                        service: None,
                         metadata: Metadata::None,
+                        findings: Vec::new(),
                     };
 
                     self.current_port = Some(port);
@@ -215,103 +346,211 @@ impl Parser {
                 _ => ParserState::Ports,
             },
             ParserState::Port => match ev {
-                Event::Start(e) if e.local_name() == b"service" => {
+                Event::Start(e) | Event::Empty(e) if e.local_name() == b"state" => {
+                    // closed and filtered ports are dropped, like the other importers do
+                    if !required(&e, b"state")?.starts_with("open") {
+                        self.current_port = None;
+                    }
+                    ParserState::Port
+                }
+                Event::Start(e) | Event::Empty(e) if e.local_name() == b"service" => {
                     // add service metadata
-                    let mut service = Service {
-                        name: None,
-                        product: None,
-                        version: None,
-                        extrainfo: None,
+                    let service = Service {
+                        name: attribute(&e, b"name")?,
+                        product: attribute(&e, b"product")?,
+                        version: attribute(&e, b"version")?,
+                        extrainfo: attribute(&e, b"extrainfo")?,
                     };
-                    for a in e.attributes() {
-                        match a.as_ref().unwrap().key {
-                            b"name" => {
-                                service.name =
-                                    Some(str::from_utf8(&a.unwrap().value).unwrap().to_string());
-                            }
-                            b"product" => {
-                                service.product =
-                                    Some(str::from_utf8(&a.unwrap().value).unwrap().to_string());
-                            }
-                            b"version" => {
-                                service.version =
-                                    Some(str::from_utf8(&a.unwrap().value).unwrap().to_string());
-                            }
-                            b"extrainfo" => {
-                                service.extrainfo =
-                                    Some(str::from_utf8(&a.unwrap().value).unwrap().to_string());
-                            }
-                            b"ostype" => {
-                                self.current_host.os =
-                                    Some(str::from_utf8(&a.unwrap().value).unwrap().to_string());
-                            }
-                            _ => {}
-                        }
+                    if let Some(os) = attribute(&e, b"ostype")? {
+                        self.current_host.os = Some(os);
                     }
 
-                    match &mut self.current_port {
-                        Some(port) => port.service = Some(service).clone(),
-                        None => unreachable!("There should be a port defined!"),
+                    if let Some(port) = &mut self.current_port {
+                        port.service = Some(service);
                     }
                     ParserState::Port
                 }
+                Event::Start(e)
+                    if e.local_name() == b"script"
+                        && e.attributes()
+                            .flatten()
+                            .any(|a| a.key == b"id" && a.value.as_ref() == b"ssh-hostkey") =>
+                {
+                    ParserState::SSH
+                }
                 Event::End(e) if e.local_name() == b"port" => {
-                    self.current_host
-                        .ports
-                        .push(self.current_port.clone().unwrap());
+                    self.current_host.ports.extend(self.current_port.take());
                     ParserState::Ports
                 }
                 _ => ParserState::Port,
             },
             ParserState::Hops => match ev {
                 Event::Empty(e) if e.local_name() == b"hop" => {
-                    // do filter magic
-                    self.current_host.hops.push(
-                        str::from_utf8(
-                            &e.attributes()
-                                .find(|a| a.as_ref().unwrap().key == b"ipaddr")
-                                .unwrap()
-                                .unwrap()
-                                .value,
-                        )
-                        .unwrap()
-                        .to_string(),
-                    );
+                    self.current_host.hops.push(required(&e, b"ipaddr")?);
 
                     ParserState::Hops
                 }
                 Event::End(e) if e.local_name() == b"trace" => ParserState::Host,
                 _ => ParserState::Hops,
             },
+            ParserState::OS => match ev {
+                Event::Start(e) | Event::Empty(e) if e.local_name() == b"osclass" => {
+                    let mut class = OsClass::default();
+                    for (key, value) in attributes(&e)? {
+                        match key.as_slice() {
+                            b"type" => class.device_type = Some(value),
+                            b"vendor" => class.vendor = Some(value),
+                            b"osfamily" => class.family = Some(value),
+                            b"osgen" => class.generation = Some(value),
+                            b"accuracy" => class.accuracy = number(b"accuracy", &value)?,
+                            _ => {}
+                        }
+                    }
+                    self.current_host.os_classes.push(class);
+                    ParserState::OS
+                }
+                Event::End(e) if e.local_name() == b"os" => {
+                    self.current_host
+                        .os_classes
+                        .sort_by_key(|c| std::cmp::Reverse(c.accuracy));
+                    ParserState::Host
+                }
+                _ => ParserState::OS,
+            },
             ParserState::Done => ParserState::Done,
 
             ParserState::SSH => match ev {
                 Event::Start(e) if e.local_name() == b"table" => ParserState::FINGERPRINT,
-
+                Event::End(e) if e.local_name() == b"script" => ParserState::Port,
                 _ => ParserState::SSH,
             },
             ParserState::FINGERPRINT => match ev {
-                Event::Empty()
-            }
+                Event::Start(e)
+                    if e.local_name() == b"elem"
+                        && e.attributes()
+                            .flatten()
+                            .any(|a| a.key == b"key" && a.value.as_ref() == b"fingerprint") =>
+                {
+                    ParserState::FINGERPRINTVALUE
+                }
+                Event::End(e) if e.local_name() == b"table" => ParserState::SSH,
+                _ => ParserState::FINGERPRINT,
+            },
+            ParserState::FINGERPRINTVALUE => match ev {
+                Event::Text(e) => {
+                    let fingerprint = e.unescaped().map_err(|e| e.to_string())?;
+                    let fingerprint = str::from_utf8(&fingerprint)
+                        .map_err(|e| e.to_string())?
+                        .to_string();
+
+                    if let Some(port) = &mut self.current_port {
+                        match &mut port.metadata {
+                            Metadata::SSH(fingerprints) => fingerprints.push(fingerprint),
+                            Metadata::None => port.metadata = Metadata::SSH(vec![fingerprint]),
+                        }
+                    }
+                    ParserState::FINGERPRINTVALUE
+                }
+                Event::End(e) if e.local_name() == b"elem" => ParserState::FINGERPRINT,
+                _ => ParserState::FINGERPRINTVALUE,
+            },
+        };
+
+        Ok(finished)
+    }
+
+    pub fn parse(filename: String) -> Result<Vec<Host>, String> {
+        Ok(Parser::parse_with_info(filename)?.1)
+    }
+
+    /// Parses the given file, or stdin if the filename is `-`.
+    pub fn parse_with_info(filename: String) -> Result<(ScanInfo, Vec<Host>), String> {
+        if filename == "-" {
+            return Parser::parse_reader(io::stdin());
         }
+
+        let file = File::open(&filename).map_err(|e| format!("{}: {}", filename, e))?;
+        Parser::parse_reader(file)
     }
 
-    pub fn parse(filename: String) -> Vec<Host> {
-        let file = File::open(filename).expect("Could not open file");
-        let reader = BufReader::new(file);
+    pub fn parse_reader<'a, R: Read + 'a>(reader: R) -> Result<(ScanInfo, Vec<Host>), String> {
+        let mut hosts = Parser::hosts(Parser::input(reader).map_err(|e| e.to_string())?);
+        let parsed = hosts.by_ref().collect::<Result<Vec<Host>, String>>()?;
+
+        Ok((hosts.info().clone(), parsed))
+    }
+
+    /// Buffers the reader and transparently decompresses gzip input (`.xml.gz`).
+    pub fn input<'a, R: Read + 'a>(reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+        let mut reader = BufReader::new(reader);
+        let gzip = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+
+        if gzip {
+            Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+        } else {
+            Ok(Box::new(reader))
+        }
+    }
 
+    /// Parses hosts one at a time, so large scans never have to be held in memory.
+    pub fn hosts<R: BufRead>(reader: R) -> Hosts<R> {
         let mut xmlfile = Reader::from_reader(reader);
+        xmlfile.trim_text(true);
 
-        let mut buf = Vec::new();
+        Hosts {
+            xmlfile,
+            buf: Vec::new(),
+            parser: Parser::new(),
+            failed: false,
+        }
+    }
+}
+
+/// Iterator over the hosts of an nmap xml-file, see `Parser::hosts`.
+pub struct Hosts<R: BufRead> {
+    xmlfile: Reader<R>,
+    buf: Vec<u8>,
+    parser: Parser,
+    failed: bool,
+}
+
+impl<R: BufRead> Hosts<R> {
+    /// Metadata of the scan. `<runstats>` follows the last host, so it is only
+    /// complete once the iterator is exhausted.
+    pub fn info(&self) -> &ScanInfo {
+        &self.parser.info
+    }
+}
+
+impl<R: BufRead> Iterator for Hosts<R> {
+    type Item = Result<Host, String>;
 
-        let mut parser: Parser = Parser::new();
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
 
         loop {
-            match xmlfile.read_event(&mut buf).unwrap() {
-                Event::Eof => break,
-                ev => parser.process(ev),
+            self.buf.clear();
+            match self.xmlfile.read_event(&mut self.buf) {
+                Ok(Event::Eof) => return None,
+                Ok(ev) => match self.parser.process(ev) {
+                    Ok(Some(host)) => return Some(Ok(host)),
+                    Ok(None) => {}
+                    Err(e) => {
+                        self.failed = true;
+                        return Some(Err(format!(
+                            "{} at byte {}",
+                            e,
+                            self.xmlfile.buffer_position()
+                        )));
+                    }
+                },
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e.to_string()));
+                }
             }
         }
-        parser.hosts
     }
 }
@@ -1,19 +1,47 @@
+use crate::category;
 use crate::detail;
+use crate::diff;
+use crate::font;
+use crate::layout;
+use crate::parser;
 use crate::server;
+use crate::theme;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
 use std::fs::File;
+use std::io;
 use std::io::Write;
 
 pub static SERVER_ENTRY_HEIGHT: u64 = 20;
 pub static SERVER_ENTRY_WIDTH: u64 = 150;
 pub static IP_ENTRY_WIDTH: u64 = 100;
 pub static PORT_ENTRY_WIDTH: u64 = 50;
+pub static FONT_SIZE: u64 = 12;
+pub static ICON_WIDTH: u64 = 20;
 static SERVER_PADDING: u64 = 10;
 
 static NETWORK_GRID_X: u64 = 8;
-static EXPECTED_SERVER_HEIGHT: u64 = 10;
+
+static GROUP_STYLE: &str = "group;";
+static LEGEND_SERVICES_LENGTH: usize = 80;
 
 pub struct Drawio {
     entries: Vec<String>,
+    theme: theme::Theme,
+    max_width: Option<u64>,
+    edges: bool,
+    collapse_ports: Option<usize>,
+    categories: Option<category::Categories>,
+    /// Id of the port row in the server boxes, keyed by ip, protocol and port.
+    port_cells: BTreeMap<(String, String, u16), String>,
+    /// Ids handed out by `unique_id`.
+    ids: HashSet<String>,
+}
+
+impl Default for Drawio {
+    fn default() -> Drawio {
+        Drawio::new()
+    }
 }
 
 impl Drawio {
@@ -24,14 +52,152 @@ impl Drawio {
         )
     }
 
-    fn mx_group_params(&mut self, id: String, geometry: &[u64; 4], parent: &String) {
-        self.entries.push(format!("<mxCell id=\"{}\" value=\"\" style=\"group;border=2px;\" parent=\"{}\" vertex=\"1\" connectable=\"0\">{}</mxCell>",
-        id, parent, Drawio::create_geometry(geometry)))
+    fn mx_group_params(&mut self, id: String, geometry: &[u64; 4], parent: &String, style: String) {
+        self.entries.push(format!("<mxCell id=\"{}\" value=\"\" style=\"{}\" parent=\"{}\" vertex=\"1\" connectable=\"0\">{}</mxCell>",
+        id, style, parent, Drawio::create_geometry(geometry)))
+    }
+
+    fn mx_cell_params(
+        &mut self,
+        id: String,
+        geometry: &[u64; 4],
+        parent: &String,
+        value: String,
+        style: String,
+    ) {
+        self.entries.push(format!(
+            "<mxCell id=\"{}\" value=\"{}\" style=\"{}\" parent=\"{}\" vertex=\"1\">{}</mxCell>",
+            id,
+            Drawio::escape(&value),
+            style,
+            parent,
+            Drawio::create_geometry(geometry)
+        ));
+    }
+
+    /// Adds a collapsible container whose title is shown in a header row. A
+    /// collapsed container only shows the header, `geometry` is its expanded
+    /// size.
+    fn mx_container(
+        &mut self,
+        id: String,
+        geometry: &[u64; 4],
+        parent: &String,
+        value: String,
+        style: String,
+        collapsed: bool,
+    ) {
+        let (collapsed, geometry) = match collapsed {
+            true => (
+                " collapsed=\"1\"",
+                format!(
+                    "<mxGeometry x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" as=\"geometry\"><mxRectangle x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" as=\"alternateBounds\"/></mxGeometry>",
+                    geometry[0], geometry[1], geometry[2], SERVER_ENTRY_HEIGHT,
+                    geometry[0], geometry[1], geometry[2], geometry[3]
+                ),
+            ),
+            false => ("", Drawio::create_geometry(geometry)),
+        };
+
+        self.entries.push(format!(
+            "<mxCell id=\"{}\" value=\"{}\" style=\"swimlane;collapsible=1;startSize={};{}\" parent=\"{}\" vertex=\"1\"{}>{}</mxCell>",
+            id,
+            Drawio::escape(&value),
+            SERVER_ENTRY_HEIGHT,
+            style,
+            parent,
+            collapsed,
+            geometry
+        ));
+    }
+
+    fn mx_edge(&mut self, id: String, source: &String, target: &String, parent: &String) {
+        self.entries.push(format!(
+            "<mxCell id=\"{}\" style=\"{}\" parent=\"{}\" source=\"{}\" target=\"{}\" edge=\"1\"><mxGeometry relative=\"1\" as=\"geometry\"/></mxCell>",
+            id, self.theme.edge, parent, source, target
+        ));
+    }
+
+    /// Joins an id prefix and a key, replacing characters that are not safe
+    /// in ids. Ids built from stable keys survive regenerating the diagram.
+    pub fn cell_id(prefix: &str, key: &str) -> String {
+        let key: String = key
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | ':' | '_' | '-' => c,
+                _ => '_',
+            })
+            .collect();
+        format!("{}-{}", prefix, key)
     }
 
-    fn mx_cell_params(&mut self, id: String, geometry: &[u64; 4], parent: &String, value: String) {
-        self.entries.push(format!("<mxCell id=\"{}\" value=\"{}\" style=\"whiteSpace=wrap;html=1;aspect=fixed;fontSize=12;\" parent=\"{}\" vertex=\"1\">{}</mxCell>",
-        id, value, parent, Drawio::create_geometry(geometry)));
+    /// Numbers ids that were handed out before, e.g. the second host with the
+    /// same address gets `host-10.0.0.1-2`.
+    fn unique_id(&mut self, id: String) -> String {
+        let mut unique = id.clone();
+        let mut count = 1;
+        while self.ids.contains(&unique) {
+            count += 1;
+            unique = format!("{}-{}", id, count);
+        }
+        self.ids.insert(unique.clone());
+        unique
+    }
+
+    /// Escapes a label for the `value` attribute. The styles set `html=1`, so
+    /// drawio reads labels as html: scan data is html escaped first and line
+    /// breaks become `<br>`, then the result is escaped for xml.
+    fn escape(value: &str) -> String {
+        let html = value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('\n', "<br>");
+
+        html.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    fn change_style(&self, item: &server::Item, change: diff::Change) -> String {
+        let colors = match change {
+            diff::Change::Added => "fillColor=#d5e8d4;strokeColor=#82b366;",
+            diff::Change::Removed => "fillColor=#f8cecc;strokeColor=#b85450;",
+            diff::Change::Changed => "fillColor=#fff2cc;strokeColor=#d6b656;",
+            diff::Change::Unchanged => "",
+        };
+        format!("{}{}", self.theme.item(item), colors)
+    }
+
+    /// Style of a row in a server box, ports are coloured by their category.
+    fn item_style(&self, item: &server::Item) -> String {
+        let category = self
+            .categories
+            .as_ref()
+            .and_then(|c| c.classify_item(item))
+            .map(|c| c.style.as_str())
+            .unwrap_or("");
+        format!("{}{}", self.theme.item(item), category)
+    }
+
+    fn network_width() -> u64 {
+        NETWORK_GRID_X * (SERVER_ENTRY_WIDTH + SERVER_PADDING) + SERVER_PADDING
+    }
+
+    /// Servers with more ports than the threshold start collapsed.
+    fn collapsed(&self, server: &server::Server) -> bool {
+        self.collapse_ports
+            .is_some_and(|threshold| server.ports() > threshold)
+    }
+
+    /// Size of a server box as it is initially shown.
+    fn server_size(&self, server: &server::Server) -> [u64; 2] {
+        let size = layout::server_size(server, self.max_width);
+        match self.collapsed(server) {
+            true => [size[0], SERVER_ENTRY_HEIGHT],
+            false => size,
+        }
     }
 
     pub fn server(
@@ -41,158 +207,581 @@ impl Drawio {
         parent: &String,
         id: String,
     ) {
-        // create group
-        let geometry = [
-            location[0],
-            location[1],
-            SERVER_ENTRY_WIDTH,
-            SERVER_ENTRY_HEIGHT * server.items.len() as u64,
-        ];
-        self.mx_group_params(format!("{}-0", id), &geometry, parent);
+        let id = self.unique_id(id);
+        let size = layout::server_size(&server, self.max_width);
+        let scope_style = match server.out_of_scope {
+            true => self.theme.out_of_scope.clone(),
+            false => String::new(),
+        };
+
+        // create the container, its header summarizes the server
+        let geometry = [location[0], location[1], size[0], size[1]];
+        let header = self.fit(server.header(), size[0]);
+        self.mx_container(
+            format!("{}-0", id),
+            &geometry,
+            parent,
+            header,
+            format!("{}{}", self.theme.server_header, scope_style),
+            self.collapsed(&server),
+        );
+
+        let addresses: Vec<String> = server
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                server::Item::IPv4(ip) | server::Item::IPv6(ip) => Some(ip.clone()),
+                _ => None,
+            })
+            .collect();
+
+        // the device icon sits left of the first row
+        self.mx_cell_params(
+            format!("{}-icon", id),
+            &[0, SERVER_ENTRY_HEIGHT, ICON_WIDTH, SERVER_ENTRY_HEIGHT],
+            &format!("{}-0", id),
+            "".to_string(),
+            format!("{}{}", server.device.style(), scope_style),
+        );
 
         // add elements to group
-        let mut i = 1;
-        let mut item_geometry = [
-            0,
-            SERVER_ENTRY_HEIGHT * (i - 1),
-            SERVER_ENTRY_WIDTH,
-            SERVER_ENTRY_HEIGHT,
-        ];
-        for item in server.items {
-            item_geometry[1] = SERVER_ENTRY_HEIGHT * (i - 1);
+        let mut item_geometry = [0, 0, size[0], SERVER_ENTRY_HEIGHT];
+        for (i, item) in (1..).zip(server.items) {
+            item_geometry[1] = SERVER_ENTRY_HEIGHT * i;
+            if i == 1 {
+                item_geometry[0] = ICON_WIDTH;
+                item_geometry[2] = size[0] - ICON_WIDTH;
+            } else {
+                item_geometry[0] = 0;
+                item_geometry[2] = size[0];
+            }
 
+            let item_id = self.unique_id(Drawio::cell_id(&id, &item.key()));
+            if let server::Item::Port(port, protocol, ..) = &item {
+                for address in &addresses {
+                    self.port_cells
+                        .insert((address.clone(), protocol.clone(), *port), item_id.clone());
+                }
+            }
+
+            let value = self.fit(item.value(), item_geometry[2]);
             self.mx_cell_params(
-                format!("{}-{}", id, i),
+                item_id,
                 &item_geometry,
                 &format!("{}-0", id),
-                item.value(),
+                value,
+                format!("{}{}", self.item_style(&item), scope_style),
             );
-            i += 1;
         }
     }
 
+    /// Packs the servers into a network box and returns its bounds.
     pub fn network(
         &mut self,
         servers: Vec<server::Server>,
         location: &[u64; 2],
         parent: &String,
         id: String,
-    ) -> u64 {
-        let mut index = 0;
+    ) -> [u64; 4] {
+        let sizes: Vec<[u64; 2]> = servers.iter().map(|s| self.server_size(s)).collect();
+        let layout = layout::shelves(&sizes, Drawio::network_width(), SERVER_PADDING);
+
+        let network_geometry = [location[0], location[1], layout.width, layout.height];
+
+        self.mx_cell_params(
+            format!("{}-bound", id),
+            &network_geometry,
+            parent,
+            "".to_string(),
+            self.theme.network.clone(),
+        );
+
+        for (index, server) in servers.into_iter().enumerate() {
+            let position = layout.positions[index];
+            let server_location = [location[0] + position[0], location[1] + position[1]];
+            let server_id = Drawio::cell_id("host", &server.key());
+
+            self.server(server, &server_location, parent, server_id);
+        }
+
+        network_geometry
+    }
+
+    /// Packs the servers outside the scope into a network box below a label
+    /// and returns the bounds of both.
+    pub fn out_of_scope(
+        &mut self,
+        servers: Vec<server::Server>,
+        location: &[u64; 2],
+        parent: &String,
+        id: String,
+    ) -> [u64; 4] {
+        self.mx_cell_params(
+            format!("{}-label", id),
+            &[
+                location[0],
+                location[1],
+                Drawio::network_width(),
+                SERVER_ENTRY_HEIGHT,
+            ],
+            parent,
+            "Out of scope".to_string(),
+            self.theme.legend.clone(),
+        );
 
-        let network_geometry = [
+        let bounds = self.network(
+            servers,
+            &[location[0], location[1] + SERVER_ENTRY_HEIGHT],
+            parent,
+            id,
+        );
+        [
             location[0],
             location[1],
-            NETWORK_GRID_X * (SERVER_ENTRY_WIDTH + SERVER_PADDING) + SERVER_PADDING,
-            ((servers.len() as u64 / NETWORK_GRID_X) + 1)
-                * EXPECTED_SERVER_HEIGHT
-                * SERVER_ENTRY_HEIGHT,
-        ];
+            bounds[2],
+            bounds[3] + SERVER_ENTRY_HEIGHT,
+        ]
+    }
+
+    pub fn changed_server(
+        &mut self,
+        host: &diff::HostChange,
+        location: &[u64; 2],
+        parent: &String,
+        id: String,
+    ) {
+        let id = self.unique_id(id);
+        let items = host.items();
+        let size = self.changed_server_size(&items);
+
+        // create group
+        let geometry = [location[0], location[1], size[0], size[1]];
+        self.mx_group_params(
+            format!("{}-0", id),
+            &geometry,
+            parent,
+            self.theme.server.clone(),
+        );
+
+        // add elements to group
+        let mut item_geometry = [0, 0, size[0], SERVER_ENTRY_HEIGHT];
+        for (i, (item, change)) in (1..).zip(items) {
+            item_geometry[1] = SERVER_ENTRY_HEIGHT * (i - 1);
+
+            let value = self.fit(item.value(), size[0]);
+            let item_id = self.unique_id(Drawio::cell_id(&id, &item.key()));
+            self.mx_cell_params(
+                item_id,
+                &item_geometry,
+                &format!("{}-0", id),
+                value,
+                match host.out_of_scope {
+                    true => format!(
+                        "{}{}",
+                        self.change_style(&item, change),
+                        self.theme.out_of_scope
+                    ),
+                    false => self.change_style(&item, change),
+                },
+            );
+        }
+    }
+
+    fn changed_server_size(&self, items: &[(server::Item, diff::Change)]) -> [u64; 2] {
+        let values: Vec<String> = items.iter().map(|(item, _)| item.value()).collect();
+        [
+            layout::column_width(&values, SERVER_ENTRY_WIDTH, self.max_width),
+            SERVER_ENTRY_HEIGHT * items.len() as u64,
+        ]
+    }
+
+    /// Lays out all changed hosts of a diff like a network, coloured by the
+    /// kind of change, and returns the bounds of the area.
+    pub fn changes(
+        &mut self,
+        diff: &diff::ScanDiff,
+        location: &[u64; 2],
+        parent: &String,
+        id: String,
+    ) -> [u64; 4] {
+        let hosts = diff.changed_hosts();
+        let sizes: Vec<[u64; 2]> = hosts
+            .iter()
+            .map(|h| self.changed_server_size(&h.items()))
+            .collect();
+        let layout = layout::shelves(&sizes, Drawio::network_width(), SERVER_PADDING);
+
+        let network_geometry = [location[0], location[1], layout.width, layout.height];
 
         self.mx_cell_params(
-            format!("network-{}-bound", id),
+            format!("changes-{}-bound", id),
             &network_geometry,
             parent,
             "".to_string(),
+            self.theme.network.clone(),
         );
 
-        for server in servers.clone() {
-            let server_location = [
-                location[0]
-                    + SERVER_PADDING
-                    + (index % NETWORK_GRID_X) * (SERVER_ENTRY_WIDTH + SERVER_PADDING),
-                location[1]
-                    + SERVER_PADDING
-                    + (index / NETWORK_GRID_X) * EXPECTED_SERVER_HEIGHT * SERVER_ENTRY_HEIGHT,
-            ];
-
-            self.server(
-                server,
+        for (index, host) in hosts.iter().enumerate() {
+            let position = layout.positions[index];
+            let server_location = [location[0] + position[0], location[1] + position[1]];
+
+            self.changed_server(
+                host,
                 &server_location,
                 parent,
-                format!("network-{}-{}", id, index),
+                Drawio::cell_id(&format!("changes-{}", id), &host.address),
+            );
+        }
+
+        network_geometry
+    }
+
+    /// Renders a title block describing the nmap run and returns its bounds.
+    pub fn legend(
+        &mut self,
+        info: &parser::ScanInfo,
+        location: &[u64; 2],
+        parent: &String,
+        id: String,
+    ) -> [u64; 4] {
+        // reports of vulnerability scanners carry no version
+        let mut rows = vec![match info.version.as_str() {
+            "" => format!("{} scan", info.scanner),
+            version => format!("{} {} scan", info.scanner, version),
+        }];
+        if let Some(report) = &info.report {
+            rows.push(format!("Report: {}", report));
+        }
+        // port lists know neither the command nor the time of the scan
+        if !info.args.is_empty() {
+            rows.push(format!("Command: {}", info.args));
+        }
+        match (info.startstr.as_str(), &info.endstr) {
+            ("", None) => {}
+            ("", Some(end)) => rows.push(format!("Time: until {}", end)),
+            (start, None) => rows.push(format!("Time: {} - unfinished", start)),
+            (start, Some(end)) => rows.push(format!("Time: {} - {}", start, end)),
+        }
+        rows.push(format!(
+            "Hosts: {} up, {} down, {} total",
+            info.hosts_up, info.hosts_down, info.hosts_total
+        ));
+        for scan in &info.scans {
+            let mut services = scan.services.clone();
+            if services.len() > LEGEND_SERVICES_LENGTH {
+                services.truncate(services[..LEGEND_SERVICES_LENGTH].rfind(',').unwrap_or(0));
+                services.push_str(",...");
+            }
+            rows.push(format!(
+                "Ports ({} {}): {} ports ({})",
+                scan.protocol, scan.scan_type, scan.numservices, services
+            ));
+        }
+
+        let width = Drawio::network_width();
+        let geometry = [
+            location[0],
+            location[1],
+            width,
+            SERVER_ENTRY_HEIGHT * rows.len() as u64,
+        ];
+        self.mx_group_params(
+            format!("{}-0", id),
+            &geometry,
+            parent,
+            GROUP_STYLE.to_string(),
+        );
+
+        let mut row_geometry = [0, 0, width, SERVER_ENTRY_HEIGHT];
+        for (i, row) in (1..).zip(rows.iter()) {
+            row_geometry[1] = SERVER_ENTRY_HEIGHT * (i - 1);
+
+            self.mx_cell_params(
+                format!("{}-{}", id, i),
+                &row_geometry,
+                &format!("{}-0", id),
+                row.to_string(),
+                self.theme.legend.clone(),
+            );
+        }
+
+        geometry
+    }
+
+    /// Renders a legend of the port categories with the number of ports in
+    /// each and returns its bounds.
+    pub fn category_legend(
+        &mut self,
+        counts: &[(&category::Category, usize)],
+        location: &[u64; 2],
+        parent: &String,
+        id: String,
+    ) -> [u64; 4] {
+        let width = Drawio::network_width();
+        let geometry = [
+            location[0],
+            location[1],
+            width,
+            SERVER_ENTRY_HEIGHT * counts.len() as u64,
+        ];
+        self.mx_group_params(
+            format!("{}-0", id),
+            &geometry,
+            parent,
+            GROUP_STYLE.to_string(),
+        );
+
+        let mut row_geometry = [0, 0, width, SERVER_ENTRY_HEIGHT];
+        for (i, (category, ports)) in (1..).zip(counts) {
+            row_geometry[1] = SERVER_ENTRY_HEIGHT * (i - 1);
+
+            self.mx_cell_params(
+                Drawio::cell_id(&id, &category.name),
+                &row_geometry,
+                &format!("{}-0", id),
+                format!(
+                    "{}: {} {}",
+                    category.name,
+                    ports,
+                    if *ports == 1 { "port" } else { "ports" }
+                ),
+                format!("{}{}", self.theme.legend, category.style),
             );
-            index += 1;
         }
-        location[1]
-            + (SERVER_PADDING * 2)
-            + ((servers.len() as u64 / NETWORK_GRID_X) + 1)
-                * EXPECTED_SERVER_HEIGHT
-                * SERVER_ENTRY_HEIGHT
+
+        geometry
     }
 
+    /// Returns the title and subtitle of a service table header.
+    fn service_header(service: &detail::Service) -> (String, String) {
+        match &service.group {
+            detail::Group::Service(identity) => {
+                let title = identity
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| "unidentified".to_string());
+                let subtitle = match (&identity.product, &identity.version) {
+                    (Some(product), Some(version)) => format!("({} {})", product, version),
+                    (Some(product), None) => format!("({})", product),
+                    _ => String::new(),
+                };
+                (title, subtitle)
+            }
+            detail::Group::Port { protocol, port } => {
+                (format!("{}/{}", port, protocol), String::new())
+            }
+            detail::Group::Product(product) => (
+                product
+                    .clone()
+                    .unwrap_or_else(|| "unidentified".to_string()),
+                String::new(),
+            ),
+            detail::Group::Host { address, name } => match name == address {
+                true => (address.clone(), String::new()),
+                false => (name.clone(), format!("({})", address)),
+            },
+        }
+    }
+
+    /// Widths of the ip, port and detail column of a service table, the ip
+    /// column is widened if the header needs more space. Tables without
+    /// details have no detail column.
+    fn service_columns(&self, service: &detail::Service) -> [u64; 3] {
+        let (title, subtitle) = Drawio::service_header(service);
+
+        let ips: Vec<String> = service.hosts.iter().map(|h| h.ip.clone()).collect();
+        let ports: Vec<String> = service.hosts.iter().map(|h| h.port.to_string()).collect();
+        let details: Vec<String> = service
+            .hosts
+            .iter()
+            .map(|h| h.detail.clone().unwrap_or_default())
+            .collect();
+        let port_width = layout::column_width(&ports, PORT_ENTRY_WIDTH, self.max_width);
+        let detail_width = match service.hosts.iter().any(|h| h.detail.is_some()) {
+            true => layout::column_width(&details, PORT_ENTRY_WIDTH, self.max_width),
+            false => 0,
+        };
+        let header_width = layout::column_width(&[title, subtitle], 0, self.max_width);
+        let ip_width = layout::column_width(&ips, IP_ENTRY_WIDTH, self.max_width)
+            .max(header_width.saturating_sub(port_width + detail_width));
+
+        [ip_width, port_width, detail_width]
+    }
+
+    /// Size of the table `service` renders.
+    pub fn service_size(&self, service: &detail::Service) -> [u64; 2] {
+        let [ip_width, port_width, detail_width] = self.service_columns(service);
+        [
+            ip_width + port_width + detail_width,
+            SERVER_ENTRY_HEIGHT * (service.hosts.len() + 3) as u64,
+        ]
+    }
+
+    /// Renders a service table and returns its bounds.
     pub fn service(
         &mut self,
         service: detail::Service,
         location: &[u64; 2],
         parent: &String,
         id: String,
-    ) {
+    ) -> [u64; 4] {
+        let id = self.unique_id(id);
+        let (title, subtitle) = Drawio::service_header(&service);
+        let [ip_width, port_width, detail_width] = self.service_columns(&service);
+        let width = ip_width + port_width + detail_width;
+
         // create group
         let geometry = [
             location[0],
             location[1],
-            IP_ENTRY_WIDTH + PORT_ENTRY_WIDTH,
+            width,
             SERVER_ENTRY_HEIGHT * (service.hosts.len() + 3) as u64,
         ];
-        self.mx_group_params(format!("{}-0", id), &geometry, parent);
+        self.mx_group_params(
+            format!("{}-0", id),
+            &geometry,
+            parent,
+            self.theme.table.clone(),
+        );
 
-        let header_geometry = [
-            0,
-            0,
-            IP_ENTRY_WIDTH + PORT_ENTRY_WIDTH,
-            SERVER_ENTRY_HEIGHT * 3,
-        ];
+        let header_geometry = [0, 0, width, SERVER_ENTRY_HEIGHT * 3];
         self.mx_cell_params(
             format!("header-{}-0", id),
             &header_geometry,
             &format!("{}-0", id),
-            format!(
-                "{}\n({} {})",
-                service.service,
-                service.product,
-                service.version.unwrap_or("unknown".to_string())
-            ),
+            match subtitle.is_empty() {
+                true => title,
+                false => format!("{}\n{}", title, subtitle),
+            },
+            self.theme.table_header.clone(),
         );
 
         // add elements to group
-        let mut i = 1;
-        let mut ip_geometry = [0, 0, IP_ENTRY_WIDTH, SERVER_ENTRY_HEIGHT];
-        let mut port_geometry = [IP_ENTRY_WIDTH, 0, PORT_ENTRY_WIDTH, SERVER_ENTRY_HEIGHT];
-        for item in service.hosts {
+        let mut ip_geometry = [0, 0, ip_width, SERVER_ENTRY_HEIGHT];
+        let mut port_geometry = [ip_width, 0, port_width, SERVER_ENTRY_HEIGHT];
+        let mut detail_geometry = [ip_width + port_width, 0, detail_width, SERVER_ENTRY_HEIGHT];
+        for (i, item) in (1..).zip(service.hosts) {
             ip_geometry[1] = SERVER_ENTRY_HEIGHT * 3 + SERVER_ENTRY_HEIGHT * (i - 1);
             port_geometry[1] = SERVER_ENTRY_HEIGHT * 3 + SERVER_ENTRY_HEIGHT * (i - 1);
+            detail_geometry[1] = port_geometry[1];
 
+            let row_id = self.unique_id(Drawio::cell_id(&id, &item.key()));
+            let ip = self.fit(item.ip.clone(), ip_width);
             self.mx_cell_params(
-                format!("{}-{}a", id, i),
+                format!("{}-ip", row_id),
                 &ip_geometry,
                 &format!("{}-0", id),
-                item.ip,
+                ip,
+                self.theme.table_row.clone(),
             );
             self.mx_cell_params(
-                format!("{}-{}b", id, i),
+                format!("{}-port", row_id),
                 &port_geometry,
                 &format!("{}-0", id),
                 format!("{}", item.port),
+                self.theme.table_row.clone(),
             );
-            i += 1;
+            if detail_width > 0 {
+                let detail = self.fit(item.detail.clone().unwrap_or_default(), detail_width);
+                self.mx_cell_params(
+                    format!("{}-detail", row_id),
+                    &detail_geometry,
+                    &format!("{}-0", id),
+                    detail,
+                    self.theme.table_row.clone(),
+                );
+            }
+
+            if self.edges {
+                let port = (item.ip.clone(), item.protocol.clone(), item.port);
+                if let Some(target) = self.port_cells.get(&port).cloned() {
+                    self.mx_edge(
+                        format!("edge-{}", row_id),
+                        &format!("{}-port", row_id),
+                        &target,
+                        parent,
+                    );
+                }
+            }
         }
+
+        geometry
     }
 
     pub fn new() -> Drawio {
-        let mut instance = Drawio {
+        Drawio {
             entries: Vec::new(),
+            theme: theme::Theme::default(),
+            max_width: None,
+            edges: false,
+            collapse_ports: None,
+            categories: None,
+            port_cells: BTreeMap::new(),
+            ids: HashSet::new(),
+        }
+    }
+
+    pub fn set_theme(&mut self, theme: theme::Theme) {
+        self.theme = theme;
+    }
+
+    /// Limits the width of server and table columns, longer values are
+    /// shortened with an ellipsis.
+    pub fn set_max_width(&mut self, max_width: Option<u64>) {
+        self.max_width = max_width;
+    }
+
+    /// Connects every row of the service tables to the port row of its
+    /// server box. Servers have to be rendered before the tables.
+    pub fn set_edges(&mut self, edges: bool) {
+        self.edges = edges;
+    }
+
+    /// Collapses server boxes with more than `ports` ports to their header.
+    pub fn set_collapse_ports(&mut self, ports: Option<usize>) {
+        self.collapse_ports = ports;
+    }
+
+    /// Colours the port rows of the server boxes by their category.
+    pub fn set_categories(&mut self, categories: Option<category::Categories>) {
+        self.categories = categories;
+    }
+
+    /// Shortens the value to fit a column of the given width.
+    fn fit(&self, value: String, width: u64) -> String {
+        match self.max_width {
+            Some(_) => font::truncate(&value, FONT_SIZE, width),
+            None => value,
+        }
+    }
+
+    /// Returns the finished diagram as drawio xml.
+    pub fn xml(&self) -> String {
+        let background = match self.theme.background.as_str() {
+            "" => String::new(),
+            color => format!(" background=\"{}\"", color),
         };
 
-        instance.entries.push("<mxGraphModel dx=\"3924\" dy=\"2527\" grid=\"1\" gridSize=\"10\" guides=\"1\" tooltips=\"1\" connect=\"1\" arrows=\"1\" fold=\"1\" page=\"1\" pageScale=\"1\" pageWidth=\"1169\" pageHeight=\"827\" math=\"0\" shadow=\"0\"><root><mxCell id=\"0\"/><mxCell id=\"1\" parent=\"0\"/>".to_string());
+        format!("<mxGraphModel dx=\"3924\" dy=\"2527\" grid=\"1\" gridSize=\"10\" guides=\"1\" tooltips=\"1\" connect=\"1\" arrows=\"1\" fold=\"1\" page=\"1\" pageScale=\"1\" pageWidth=\"1169\" pageHeight=\"827\" math=\"0\" shadow=\"0\"{}><root><mxCell id=\"0\"/><mxCell id=\"1\" parent=\"0\"/>{}</root></mxGraphModel>",
+            background,
+            self.entries.join(""))
+    }
 
-        instance
+    pub fn export(&self, filename: String) -> io::Result<()> {
+        let mut writer = File::create(filename)?;
+        write!(writer, "{}", self.xml())
     }
+}
 
-    pub fn export(&mut self, filename: String) {
-        self.entries.push("</root></mxGraphModel>".to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let mut writer = File::create(filename).expect("Could not create file!");
-        write!(writer, "{}", self.entries.join("")).expect("Could not write to file!");
+    #[test]
+    fn labels_are_escaped_as_html_and_xml() {
+        assert_eq!(
+            Drawio::escape("<b>AT&T</b> \"router\"\nssh"),
+            "&amp;lt;b&amp;gt;AT&amp;amp;T&amp;lt;/b&amp;gt; &quot;router&quot;&lt;br&gt;ssh"
+        );
     }
 }
@@ -0,0 +1,212 @@
+use crate::filter;
+use crate::parser;
+use crate::parser::Host;
+use std::fs;
+use std::net;
+
+/// An entry of a scope file.
+#[derive(Debug, Clone)]
+enum Target {
+    Network(net::IpAddr, u8),
+    /// An inclusive range of addresses, `10.0.0.1-20` or `10.0.0.1-10.0.0.20`.
+    Range(net::IpAddr, net::IpAddr),
+    /// A hostname, `*.example.com` matches all subdomains.
+    Hostname(String),
+}
+
+impl Target {
+    /// Entries starting with a digit or containing a `:` are addresses,
+    /// everything else is a hostname.
+    fn parse(entry: &str) -> Result<Target, String> {
+        let address = entry.starts_with(|c: char| c.is_ascii_digit()) || entry.contains(':');
+        if !address && !entry.contains('/') {
+            return Ok(Target::Hostname(entry.to_lowercase()));
+        }
+
+        match entry.split_once('-') {
+            Some((start, end)) => Target::range(start.trim(), end.trim()),
+            None => {
+                let (address, prefix) = filter::network(entry)?;
+                Ok(Target::Network(address, prefix))
+            }
+        }
+    }
+
+    /// Parses a range, its end is either a full address or, for ipv4, the
+    /// last octet.
+    fn range(start: &str, end: &str) -> Result<Target, String> {
+        let parse = |address: &str| {
+            address
+                .parse::<net::IpAddr>()
+                .map_err(|_| format!("\"{}\" is not an address", address))
+        };
+        let start = parse(start)?;
+        let end = match (start, end.parse::<u8>()) {
+            (net::IpAddr::V4(start), Ok(last)) => {
+                let [a, b, c, _] = start.octets();
+                net::IpAddr::from([a, b, c, last])
+            }
+            _ => parse(end)?,
+        };
+
+        if start.is_ipv4() != end.is_ipv4() || start > end {
+            return Err(format!("\"{}-{}\" is not an address range", start, end));
+        }
+        Ok(Target::Range(start, end))
+    }
+
+    /// Whether the address or hostname is covered by the target.
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Target::Network(network, prefix) => filter::in_network(name, network, *prefix),
+            Target::Range(start, end) => name
+                .parse::<net::IpAddr>()
+                .is_ok_and(|address| (start..=end).contains(&&address)),
+            Target::Hostname(hostname) => {
+                let name = name.to_lowercase();
+                match hostname.strip_prefix("*.") {
+                    Some(domain) => name.ends_with(&format!(".{}", domain)),
+                    None => name == *hostname,
+                }
+            }
+        }
+    }
+}
+
+/// The systems a scan is allowed to cover, loaded from files with one
+/// network, address range, address or hostname per line and exclusions
+/// prefixed by `!`:
+///
+/// ```text
+/// # office network without the firewall and the printers
+/// 10.0.0.0/24
+/// !10.0.0.1
+/// !10.0.0.200-220
+/// *.example.com
+/// ```
+///
+/// Without any included entries everything but the exclusions is in scope.
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    include: Vec<Target>,
+    exclude: Vec<Target>,
+}
+
+impl Scope {
+    pub fn parse(content: &str) -> Result<Scope, String> {
+        let mut scope = Scope::default();
+
+        for line in content.lines() {
+            let entry = line.split('#').next().unwrap_or("").trim();
+            if entry.is_empty() {
+                continue;
+            }
+
+            match entry.strip_prefix('!') {
+                Some(excluded) => scope.exclude.push(Target::parse(excluded.trim())?),
+                None => scope.include.push(Target::parse(entry)?),
+            }
+        }
+
+        Ok(scope)
+    }
+
+    pub fn load(filename: &str) -> Result<Scope, String> {
+        let content = fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        Scope::parse(&content).map_err(|e| format!("{}: {}", filename, e))
+    }
+
+    /// Whether the host is in scope, judged by its ip addresses and hostnames.
+    /// A host is out of scope if any of them is excluded.
+    pub fn contains(&self, host: &Host) -> bool {
+        let names: Vec<&str> = host
+            .addresses
+            .iter()
+            .filter(|a| !matches!(a.addr_type, parser::AddrType::MAC))
+            .map(|a| a.address.as_str())
+            .chain(host.hostnames.iter().map(|h| h.as_str()))
+            .collect();
+        let any = |targets: &[Target]| {
+            targets
+                .iter()
+                .any(|t| names.iter().any(|name| t.matches(name)))
+        };
+
+        (self.include.is_empty() || any(&self.include)) && !any(&self.exclude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(address: &str, hostname: Option<&str>) -> Host {
+        Host {
+            addresses: vec![parser::IpAddr {
+                address: address.to_string(),
+                addr_type: match address.contains(':') {
+                    true => parser::AddrType::IPv6,
+                    false => parser::AddrType::IPv4,
+                },
+            }],
+            hostnames: hostname.iter().map(|h| h.to_string()).collect(),
+            ..Host::default()
+        }
+    }
+
+    fn contains(scope: &str, address: &str) -> bool {
+        Scope::parse(scope).unwrap().contains(&host(address, None))
+    }
+
+    #[test]
+    fn networks_and_addresses() {
+        let scope = "# office\n10.0.0.0/24\n!10.0.0.1 # firewall\n2001:db8::/32";
+
+        assert!(contains(scope, "10.0.0.2"));
+        assert!(!contains(scope, "10.0.0.1"));
+        assert!(!contains(scope, "10.0.1.2"));
+        assert!(contains(scope, "2001:db8::1"));
+        assert!(!contains(scope, "2001:db9::1"));
+    }
+
+    #[test]
+    fn ranges() {
+        assert!(contains("10.0.0.1-20", "10.0.0.20"));
+        assert!(!contains("10.0.0.1-20", "10.0.0.21"));
+        assert!(contains("10.0.0.250 - 10.0.1.5", "10.0.1.1"));
+        assert!(!contains("10.0.0.250 - 10.0.1.5", "10.0.2.1"));
+        assert!(contains("2001:db8::1-2001:db8::ff", "2001:db8::10"));
+        assert!(!contains("10.0.0.0/24\n!10.0.0.1-20", "10.0.0.5"));
+        assert!(contains("10.0.0.0/24\n!10.0.0.1-20", "10.0.0.21"));
+    }
+
+    #[test]
+    fn hostnames() {
+        let scope = Scope::parse("*.example.com\nweb-01.example.org\n!db.example.com").unwrap();
+
+        assert!(scope.contains(&host("192.0.2.1", Some("WWW.example.com"))));
+        assert!(scope.contains(&host("192.0.2.1", Some("web-01.example.org"))));
+        assert!(!scope.contains(&host("192.0.2.1", Some("example.com"))));
+        assert!(!scope.contains(&host("192.0.2.1", Some("db.example.com"))));
+        assert!(Scope::parse("!db.example.com")
+            .unwrap()
+            .contains(&host("192.0.2.1", None)));
+    }
+
+    #[test]
+    fn invalid_entries() {
+        let error = |scope: &str| Scope::parse(scope).unwrap_err();
+
+        assert_eq!(error("10.0.0.300"), "\"10.0.0.300\" is not an address");
+        assert_eq!(
+            error("10.0.0.20-1"),
+            "\"10.0.0.20-10.0.0.1\" is not an address range"
+        );
+        assert_eq!(error("!10.0.0.1-x"), "\"x\" is not an address");
+        assert_eq!(
+            error("10.0.0.1-::1"),
+            "\"10.0.0.1-::1\" is not an address range"
+        );
+        assert_eq!(error("10.0.0.0/33"), "\"33\" is not a prefix length");
+    }
+}
@@ -0,0 +1,48 @@
+/// Ports worth a look on a first pass through a network: remote access,
+/// cleartext protocols, databases, file shares, directories and the usual web
+/// ports. RPC noise like 135 and 139 is left out.
+static INTERESTING: &str = "21-23,25,53,80,88,110-111,143,161,389,443,445,512-514,636,873,\
+1433,1521,2049,2375-2376,3306,3389,5432,5900-5910,5985-5986,6379,8000,8080,8443,\
+9200,11211,27017";
+
+/// Which ports are drawn. A port is drawn if it is included, or nothing is
+/// included explicitly, and not excluded.
+#[derive(Debug, Clone, Default)]
+pub struct PortSelection {
+    pub include: Vec<(u16, u16)>,
+    pub exclude: Vec<(u16, u16)>,
+}
+
+impl PortSelection {
+    /// Parses a list of ports and ranges like `135,139,1000-2000`.
+    pub fn ranges(list: &str) -> Option<Vec<(u16, u16)>> {
+        list.split(',')
+            .map(|range| {
+                let range = range.trim();
+                let (first, last) = range.split_once('-').unwrap_or((range, range));
+                let (first, last) = (first.parse().ok()?, last.parse().ok()?);
+                match first <= last {
+                    true => Some((first, last)),
+                    false => None,
+                }
+            })
+            .collect()
+    }
+
+    /// The built-in "interesting" profile.
+    pub fn interesting() -> PortSelection {
+        PortSelection {
+            include: PortSelection::ranges(INTERESTING).unwrap(),
+            exclude: Vec::new(),
+        }
+    }
+
+    pub fn shows(&self, port: u16) -> bool {
+        let within = |ranges: &[(u16, u16)]| {
+            ranges
+                .iter()
+                .any(|(first, last)| (*first..=*last).contains(&port))
+        };
+        (self.include.is_empty() || within(&self.include)) && !within(&self.exclude)
+    }
+}
@@ -1,13 +1,18 @@
+use crate::icon;
 use crate::parser;
+use crate::selection::PortSelection;
 
 #[derive(Clone)]
 pub enum Item {
     FriendlyName(String),
     IPv4(String),
     IPv6(String),
-    Port(u16, String, String),
+    /// Port, protocol, service name and the findings, see `findings`.
+    Port(u16, String, String, Option<(parser::Severity, usize)>),
     OS(String),
     MAC(String),
+    /// Number of ports left out by the port selection.
+    More(usize),
 }
 
 impl Item {
@@ -16,30 +21,130 @@ impl Item {
             Item::FriendlyName(name) => name.to_string(),
             Item::IPv4(ip) => format!("IPv4: {}", ip),
             Item::IPv6(ip) => format!("IPv6: {}", ip),
-            Item::Port(port, protocol, application) => {
+            Item::Port(port, protocol, application, None) => {
                 format!("{}/{} {}", port, protocol, application)
             }
+            Item::Port(port, protocol, application, Some((severity, count))) => format!(
+                "{}/{} {} [{} ×{}]",
+                port,
+                protocol,
+                application,
+                severity.name(),
+                count
+            ),
             Item::OS(name) => format!("OS: {}", name),
             Item::MAC(mac) => format!("MAC: {}", mac),
+            Item::More(1) => "+1 more port".to_string(),
+            Item::More(count) => format!("+{} more ports", count),
+        }
+    }
+
+    /// Identifies the item within its server across scans, used for cell ids.
+    pub fn key(&self) -> String {
+        match self {
+            Item::FriendlyName(name) => format!("name-{}", name),
+            Item::IPv4(ip) => format!("ipv4-{}", ip),
+            Item::IPv6(ip) => format!("ipv6-{}", ip),
+            Item::Port(port, protocol, ..) => format!("port-{}-{}", protocol, port),
+            Item::OS(_) => "os".to_string(),
+            Item::MAC(mac) => format!("mac-{}", mac),
+            Item::More(_) => "more".to_string(),
         }
     }
 }
 
+/// The highest severity of the findings on a port and their number.
+/// Informational findings are left out, vulnerability scanners report dozens
+/// of them on every port.
+pub fn findings(port: &parser::Port) -> Option<(parser::Severity, usize)> {
+    let severities: Vec<parser::Severity> = port
+        .findings
+        .iter()
+        .map(|f| f.severity)
+        .filter(|s| *s > parser::Severity::Info)
+        .collect();
+    Some((*severities.iter().max()?, severities.len()))
+}
+
 #[derive(Clone)]
 pub struct Server {
     pub items: Vec<Item>,
+    /// Icon shown next to the first row.
+    pub device: icon::Device,
+    /// Drawn greyed out, see `scope::Scope`.
+    pub out_of_scope: bool,
+    key: String,
 }
 
 impl Server {
-    fn new() -> Server {
-        Server { items: Vec::new() }
+    fn new(device: icon::Device, key: String) -> Server {
+        Server {
+            items: Vec::new(),
+            device,
+            out_of_scope: false,
+            key,
+        }
+    }
+
+    /// Title of the collapsible server box: the first hostname or address and
+    /// the number of ports.
+    pub fn header(&self) -> String {
+        let name = self
+            .items
+            .iter()
+            .find_map(|item| match item {
+                Item::FriendlyName(name) => Some(name),
+                _ => None,
+            })
+            .or_else(|| {
+                self.items.iter().find_map(|item| match item {
+                    Item::IPv4(address) | Item::IPv6(address) | Item::MAC(address) => Some(address),
+                    _ => None,
+                })
+            })
+            .cloned()
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let ports = self.ports();
+        format!(
+            "{} ({} {})",
+            name,
+            ports,
+            if ports == 1 { "port" } else { "ports" }
+        )
+    }
+
+    /// Identifies the server across scans, see `parser::Host::key`.
+    pub fn key(&self) -> String {
+        self.key.clone()
+    }
+
+    /// Number of ports, including those left out by the port selection.
+    pub fn ports(&self) -> usize {
+        self.items
+            .iter()
+            .map(|item| match item {
+                Item::Port(..) => 1,
+                Item::More(count) => *count,
+                _ => 0,
+            })
+            .sum()
     }
 
-    pub fn into_items(host: parser::Host) -> Server {
-        let mut server = Server::new();
+    /// Itemizes the host, ports not shown by `selection` are summed up in a
+    /// single row.
+    pub fn into_items(host: parser::Host, selection: &PortSelection) -> Server {
+        let key = host.key().unwrap_or_else(|| "unknown".to_string());
+        let mut server = Server::new(icon::Device::detect(&host), key);
 
+        // nmap lists a name given on the command line and the same name
+        // resolved from the address
+        let mut names: Vec<String> = Vec::new();
         for name in host.hostnames {
-            server.items.push(Item::FriendlyName(name));
+            if !names.contains(&name) {
+                names.push(name.clone());
+                server.items.push(Item::FriendlyName(name));
+            }
         }
 
         for addr in host.addresses {
@@ -56,18 +161,28 @@ impl Server {
             }
         }
 
+        let mut hidden = 0;
         for port in host.ports {
+            if !selection.shows(port.port) {
+                hidden += 1;
+                continue;
+            }
+
+            let findings = findings(&port);
             let service_name = match port.service {
                 Some(service) => service.name.unwrap_or_else(|| "unknown".to_string()),
                 None => "unknown".to_string(),
             };
             server
                 .items
-                .push(Item::Port(port.port, port.protocol, service_name));
+                .push(Item::Port(port.port, port.protocol, service_name, findings));
+        }
+        if hidden > 0 {
+            server.items.push(Item::More(hidden));
         }
 
-        if host.os.is_some() {
-            server.items.push(Item::OS(host.os.unwrap()));
+        if let Some(os) = host.os {
+            server.items.push(Item::OS(os));
         }
 
         server
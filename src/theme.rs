@@ -0,0 +1,146 @@
+use crate::server;
+use serde::Deserialize;
+use std::fs;
+
+/// Drawio style strings for every kind of element on the canvas.
+///
+/// Themes are loaded from TOML files with one key per element, keys missing
+/// from the file are taken from the light theme:
+///
+/// ```toml
+/// network = "rounded=1;whiteSpace=wrap;html=1;fillColor=#dae8fc;"
+/// port = "whiteSpace=wrap;html=1;fontSize=12;fontFamily=Courier New;"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Background colour of the diagram, empty for none.
+    pub background: String,
+    pub network: String,
+    /// Group of the rows of a changed host in `diff` mode.
+    pub server: String,
+    /// Header of a server box, drawn as a collapsible swimlane.
+    pub server_header: String,
+    pub friendly_name: String,
+    pub ipv4: String,
+    pub ipv6: String,
+    pub port: String,
+    pub os: String,
+    pub mac: String,
+    pub table: String,
+    pub table_header: String,
+    pub table_row: String,
+    pub legend: String,
+    pub edge: String,
+    /// Appended to the styles of hosts outside the scope.
+    pub out_of_scope: String,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::light()
+    }
+}
+
+impl Theme {
+    fn uniform(
+        background: &str,
+        group: &str,
+        header: &str,
+        cell: &str,
+        legend: &str,
+        edge: &str,
+    ) -> Theme {
+        Theme {
+            background: background.to_string(),
+            network: cell.to_string(),
+            server: group.to_string(),
+            server_header: header.to_string(),
+            friendly_name: cell.to_string(),
+            ipv4: cell.to_string(),
+            ipv6: cell.to_string(),
+            port: cell.to_string(),
+            os: cell.to_string(),
+            mac: cell.to_string(),
+            table: group.to_string(),
+            table_header: cell.to_string(),
+            table_row: cell.to_string(),
+            legend: legend.to_string(),
+            edge: edge.to_string(),
+            out_of_scope: "opacity=40;".to_string(),
+        }
+    }
+
+    pub fn light() -> Theme {
+        Theme::uniform(
+            "",
+            "group;border=2px;",
+            "html=1;fontSize=12;fontStyle=1;align=left;spacingLeft=4;",
+            "whiteSpace=wrap;html=1;aspect=fixed;fontSize=12;",
+            "whiteSpace=wrap;html=1;fontSize=12;align=left;spacingLeft=4;",
+            "edgeStyle=orthogonalEdgeStyle;rounded=0;orthogonalLoop=1;jettySize=auto;html=1;endArrow=none;opacity=50;",
+        )
+    }
+
+    pub fn dark() -> Theme {
+        let mut theme = Theme::uniform(
+            "#1e1e1e",
+            "group;border=2px;",
+            "html=1;fontSize=12;fontStyle=1;align=left;spacingLeft=4;fillColor=#3c3c3c;swimlaneFillColor=#2d2d2d;strokeColor=#8c8c8c;fontColor=#ffffff;",
+            "whiteSpace=wrap;html=1;aspect=fixed;fontSize=12;fillColor=#2d2d2d;strokeColor=#8c8c8c;fontColor=#e6e6e6;",
+            "whiteSpace=wrap;html=1;fontSize=12;align=left;spacingLeft=4;fillColor=#2d2d2d;strokeColor=#8c8c8c;fontColor=#e6e6e6;",
+            "edgeStyle=orthogonalEdgeStyle;rounded=0;orthogonalLoop=1;jettySize=auto;html=1;endArrow=none;opacity=50;strokeColor=#b3b3b3;",
+        );
+        theme.network = "whiteSpace=wrap;html=1;aspect=fixed;fontSize=12;fillColor=#252526;strokeColor=#8c8c8c;".to_string();
+        theme.friendly_name = "whiteSpace=wrap;html=1;aspect=fixed;fontSize=12;fillColor=#3c3c3c;strokeColor=#8c8c8c;fontColor=#ffffff;fontStyle=1;".to_string();
+        theme.table_header = theme.friendly_name.clone();
+        theme
+    }
+
+    /// Black on white without fills, for printing.
+    pub fn print() -> Theme {
+        let mut theme = Theme::uniform(
+            "",
+            "group;border=2px;",
+            "html=1;fontSize=12;fontStyle=1;align=left;spacingLeft=4;fillColor=none;strokeColor=#000000;fontColor=#000000;",
+            "whiteSpace=wrap;html=1;aspect=fixed;fontSize=12;fillColor=none;strokeColor=#000000;fontColor=#000000;",
+            "whiteSpace=wrap;html=1;fontSize=12;align=left;spacingLeft=4;fillColor=none;strokeColor=#000000;fontColor=#000000;",
+            "edgeStyle=orthogonalEdgeStyle;rounded=0;orthogonalLoop=1;jettySize=auto;html=1;endArrow=none;strokeColor=#000000;dashed=1;",
+        );
+        theme.friendly_name = "whiteSpace=wrap;html=1;aspect=fixed;fontSize=12;fillColor=none;strokeColor=#000000;fontColor=#000000;fontStyle=1;".to_string();
+        theme.table_header = theme.friendly_name.clone();
+        theme
+    }
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "light" => Some(Theme::light()),
+            "dark" => Some(Theme::dark()),
+            "print" => Some(Theme::print()),
+            _ => None,
+        }
+    }
+
+    /// Loads a built-in theme by name, or a theme file otherwise.
+    pub fn load(name: &str) -> Result<Theme, String> {
+        match Theme::builtin(name) {
+            Some(theme) => Ok(theme),
+            None => {
+                let content = fs::read_to_string(name).map_err(|e| format!("{}: {}", name, e))?;
+                toml::from_str(&content).map_err(|e| format!("{}: {}", name, e))
+            }
+        }
+    }
+
+    /// Style of a row in a server box.
+    pub fn item(&self, item: &server::Item) -> &String {
+        match item {
+            server::Item::FriendlyName(_) => &self.friendly_name,
+            server::Item::IPv4(_) => &self.ipv4,
+            server::Item::IPv6(_) => &self.ipv6,
+            server::Item::Port(..) | server::Item::More(_) => &self.port,
+            server::Item::OS(_) => &self.os,
+            server::Item::MAC(_) => &self.mac,
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::str;
+
+/// Prefixes of the cell ids `renderer::Drawio` generates. Cells with other
+/// ids were added by hand and are never touched.
+static GENERATED_PREFIXES: [&str; 8] = [
+    "legend-",
+    "categories-",
+    "network-",
+    "host-",
+    "table-",
+    "header-table-",
+    "edge-table-",
+    "changes-",
+];
+
+/// Style appended to generated cells that are missing from the new scan.
+static VANISHED_STYLE: &str = "dashed=1;opacity=40;";
+
+/// An xml element of a drawio file. Attribute values are kept escaped, so
+/// they are written back exactly as they were read.
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn from_start(e: &BytesStart) -> Result<Element, String> {
+        Ok(Element {
+            name: String::from_utf8_lossy(e.name()).to_string(),
+            attributes: e
+                .attributes()
+                .map(|a| {
+                    let a = a.map_err(|e| e.to_string())?;
+                    Ok((
+                        String::from_utf8_lossy(a.key).to_string(),
+                        String::from_utf8_lossy(&a.value).to_string(),
+                    ))
+                })
+                .collect::<Result<_, String>>()?,
+            children: Vec::new(),
+        })
+    }
+
+    /// Parses the document element of a drawio file.
+    fn parse(xml: &str) -> Result<Element, String> {
+        let mut reader = Reader::from_str(xml);
+        reader.trim_text(true);
+
+        let mut buf = Vec::new();
+        let mut stack: Vec<Element> = Vec::new();
+        loop {
+            match reader.read_event(&mut buf).map_err(|e| e.to_string())? {
+                Event::Start(e) => stack.push(Element::from_start(&e)?),
+                Event::Empty(e) => {
+                    let element = Element::from_start(&e)?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                Event::End(_) => {
+                    let element = stack.pop().ok_or("unbalanced diagram")?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                Event::Text(_) => {
+                    return Err(
+                        "compressed diagrams are not supported, save the diagram uncompressed"
+                            .to_string(),
+                    )
+                }
+                Event::Eof => return Err("the diagram is empty".to_string()),
+                _ => {}
+            }
+            buf.clear();
+        }
+    }
+
+    fn xml(&self) -> String {
+        let attributes: String = self
+            .attributes
+            .iter()
+            .map(|(key, value)| format!(" {}=\"{}\"", key, value))
+            .collect();
+
+        if self.children.is_empty() {
+            format!("<{}{}/>", self.name, attributes)
+        } else {
+            let children: String = self.children.iter().map(|c| c.xml()).collect();
+            format!("<{}{}>{}</{}>", self.name, attributes, children, self.name)
+        }
+    }
+
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn set_attribute(&mut self, key: &str, value: Option<&str>) {
+        let position = self.attributes.iter().position(|(k, _)| k == key);
+        match (position, value) {
+            (Some(i), Some(value)) => self.attributes[i].1 = value.to_string(),
+            (Some(i), None) => {
+                self.attributes.remove(i);
+            }
+            (None, Some(value)) => self.attributes.push((key.to_string(), value.to_string())),
+            (None, None) => {}
+        }
+    }
+
+    fn child_mut(&mut self, name: &str) -> Option<&mut Element> {
+        self.children.iter_mut().find(|c| c.name == name)
+    }
+
+    /// Finds the `<root>` holding the cells of the first diagram, in a bare
+    /// `<mxGraphModel>` or an `<mxfile>`.
+    fn root_mut(&mut self) -> Option<&mut Element> {
+        if self.name == "root" {
+            return Some(self);
+        }
+        self.children
+            .iter_mut()
+            .find(|c| matches!(c.name.as_str(), "diagram" | "mxGraphModel" | "root"))?
+            .root_mut()
+    }
+
+    fn id(&self) -> &str {
+        self.attribute("id").unwrap_or("")
+    }
+
+    /// The `<mxCell>` of a cell. Cells with custom properties are wrapped in
+    /// an `<object>` or `<UserObject>` carrying the id and label.
+    fn cell(&self) -> &Element {
+        match self.name.as_str() {
+            "mxCell" => self,
+            _ => self
+                .children
+                .iter()
+                .find(|c| c.name == "mxCell")
+                .unwrap_or(self),
+        }
+    }
+
+    fn cell_mut(&mut self) -> &mut Element {
+        match self.children.iter().position(|c| c.name == "mxCell") {
+            Some(i) if self.name != "mxCell" => &mut self.children[i],
+            _ => self,
+        }
+    }
+
+    fn generated(&self) -> bool {
+        GENERATED_PREFIXES.iter().any(|p| self.id().starts_with(p))
+    }
+
+    fn style(&self) -> String {
+        self.cell()
+            .attribute("style")
+            .unwrap_or("")
+            .replace(VANISHED_STYLE, "")
+    }
+
+    fn mark_vanished(&mut self) {
+        let style = format!("{}{}", self.style(), VANISHED_STYLE);
+        self.cell_mut().set_attribute("style", Some(&style));
+    }
+}
+
+/// Updates an existing generated cell from the new rendering. The label, size
+/// and folding come from the new cell, the style, the position of top level
+/// cells and the waypoints of edges are kept, as they may have been edited by
+/// hand.
+fn merge(existing: &Element, generated: &Element) -> Element {
+    let mut merged = existing.clone();
+    let label_key = match merged.name.as_str() {
+        "mxCell" => "value",
+        _ => "label",
+    };
+    merged.set_attribute(label_key, generated.cell().attribute("value"));
+
+    let style = existing.style();
+    let top_level = existing.cell().attribute("parent") == Some("1");
+    let position = existing
+        .cell()
+        .children
+        .iter()
+        .find(|c| c.name == "mxGeometry")
+        .map(|g| {
+            (
+                g.attribute("x").map(str::to_string),
+                g.attribute("y").map(str::to_string),
+            )
+        });
+
+    let cell = merged.cell_mut();
+    cell.set_attribute("style", Some(&style));
+    cell.set_attribute("collapsed", generated.cell().attribute("collapsed"));
+    if cell.attribute("edge") != Some("1") {
+        cell.children = generated.cell().children.clone();
+    }
+
+    if let (true, Some((x, y))) = (top_level, position) {
+        if let Some(geometry) = cell.child_mut("mxGeometry") {
+            geometry.set_attribute("x", x.as_deref());
+            geometry.set_attribute("y", y.as_deref());
+            if let Some(bounds) = geometry.child_mut("mxRectangle") {
+                bounds.set_attribute("x", x.as_deref());
+                bounds.set_attribute("y", y.as_deref());
+            }
+        }
+    }
+
+    merged
+}
+
+/// Updates a drawio diagram that may have been edited by hand with a new
+/// rendering of `generated`, matching cells by id, cells sharing an id in the
+/// order they appear:
+///
+/// * cells of both are updated, keeping the style and the position of
+///   top level cells from the existing diagram,
+/// * new cells are added,
+/// * generated cells missing from the new rendering are kept but drawn
+///   dashed and faded,
+/// * cells added by hand are kept as they are.
+pub fn update(existing: &str, generated: &str) -> Result<String, String> {
+    let mut document = Element::parse(existing)?;
+    let mut rendering = Element::parse(generated)?;
+
+    let mut new_cells: BTreeMap<String, VecDeque<Element>> = BTreeMap::new();
+    let mut order = Vec::new();
+    let no_diagram = || "no diagram found".to_string();
+    for cell in rendering
+        .root_mut()
+        .ok_or_else(no_diagram)?
+        .children
+        .drain(..)
+    {
+        order.push(cell.id().to_string());
+        new_cells
+            .entry(cell.id().to_string())
+            .or_default()
+            .push_back(cell);
+    }
+
+    let root = document.root_mut().ok_or_else(no_diagram)?;
+    let mut cells = Vec::new();
+    for mut cell in root.children.drain(..) {
+        match new_cells.get_mut(cell.id()).and_then(|c| c.pop_front()) {
+            Some(new_cell) if cell.generated() => cells.push(merge(&cell, &new_cell)),
+            // the layer cells "0" and "1"
+            Some(_) => cells.push(cell),
+            None => {
+                if cell.generated() {
+                    cell.mark_vanished();
+                }
+                cells.push(cell);
+            }
+        }
+    }
+
+    // new cells come after all existing ones, their parents precede them
+    for id in order {
+        if let Some(cell) = new_cells.get_mut(&id).and_then(|c| c.pop_front()) {
+            cells.push(cell);
+        }
+    }
+
+    root.children = cells;
+    Ok(document.xml())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagram(cells: &str) -> String {
+        format!(
+            "<mxGraphModel><root><mxCell id=\"0\"/><mxCell id=\"1\" parent=\"0\"/>{}</root></mxGraphModel>",
+            cells
+        )
+    }
+
+    fn cell(id: &str, parent: &str, value: &str, style: &str, x: u64) -> String {
+        format!(
+            "<mxCell id=\"{}\" value=\"{}\" style=\"{}\" parent=\"{}\" vertex=\"1\"><mxGeometry x=\"{}\" y=\"10\" width=\"150\" height=\"20\" as=\"geometry\"/></mxCell>",
+            id, value, style, parent, x
+        )
+    }
+
+    fn parse_cells(xml: &str) -> Vec<Element> {
+        Element::parse(xml)
+            .unwrap()
+            .root_mut()
+            .unwrap()
+            .children
+            .clone()
+    }
+
+    fn find<'a>(cells: &'a [Element], id: &str) -> &'a Element {
+        cells.iter().find(|c| c.id() == id).unwrap()
+    }
+
+    fn x(cell: &Element) -> &str {
+        cell.children[0].attribute("x").unwrap()
+    }
+
+    #[test]
+    fn keeps_style_and_takes_label() {
+        let existing = diagram(&cell("host-a-0", "1", "old", "fillColor=#ff0000;", 10));
+        let generated = diagram(&cell("host-a-0", "1", "new", "fillColor=#ffffff;", 10));
+
+        let cells = parse_cells(&update(&existing, &generated).unwrap());
+        let host = find(&cells, "host-a-0");
+        assert_eq!(host.attribute("value"), Some("new"));
+        assert_eq!(host.attribute("style"), Some("fillColor=#ff0000;"));
+    }
+
+    #[test]
+    fn marks_vanished_cells() {
+        let existing = diagram(&cell("host-a-0", "1", "a", "", 10));
+        let generated = diagram("");
+
+        let updated = update(&existing, &generated).unwrap();
+        let cells = parse_cells(&updated);
+        assert_eq!(
+            find(&cells, "host-a-0").attribute("style"),
+            Some(VANISHED_STYLE)
+        );
+
+        // the mark is dropped once the cell is back in a scan
+        let generated = diagram(&cell("host-a-0", "1", "a", "", 10));
+        let cells = parse_cells(&update(&updated, &generated).unwrap());
+        assert_eq!(find(&cells, "host-a-0").attribute("style"), Some(""));
+    }
+
+    #[test]
+    fn keeps_cells_added_by_hand() {
+        let existing = diagram(&cell("note", "1", "firewall", "shape=note;", 10));
+        let generated = diagram(&cell("host-a-0", "1", "a", "", 10));
+
+        let cells = parse_cells(&update(&existing, &generated).unwrap());
+        let note = find(&cells, "note");
+        assert_eq!(note.attribute("value"), Some("firewall"));
+        assert_eq!(note.attribute("style"), Some("shape=note;"));
+        assert!(cells.iter().any(|c| c.id() == "host-a-0"));
+    }
+
+    #[test]
+    fn keeps_position_of_top_level_cells_only() {
+        let existing = diagram(&format!(
+            "{}{}",
+            cell("host-a-0", "1", "a", "", 500),
+            cell("host-a-port-tcp-22", "host-a-0", "22/tcp ssh", "", 30)
+        ));
+        let generated = diagram(&format!(
+            "{}{}",
+            cell("host-a-0", "1", "a", "", 10),
+            cell("host-a-port-tcp-22", "host-a-0", "22/tcp ssh", "", 0)
+        ));
+
+        let cells = parse_cells(&update(&existing, &generated).unwrap());
+        assert_eq!(x(find(&cells, "host-a-0")), "500");
+        assert_eq!(x(find(&cells, "host-a-port-tcp-22")), "0");
+    }
+
+    #[test]
+    fn matches_repeated_ids_in_order() {
+        let repeated = format!(
+            "{}{}",
+            cell("host-a-name-a", "host-a-0", "a", "", 0),
+            cell("host-a-name-a", "host-a-0", "a", "", 0)
+        );
+
+        let cells = parse_cells(&update(&diagram(&repeated), &diagram(&repeated)).unwrap());
+        let names: Vec<&Element> = cells.iter().filter(|c| c.id() == "host-a-name-a").collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.iter().all(|c| c.attribute("style") == Some("")));
+    }
+}